@@ -1,26 +1,317 @@
-pub trait Queue<T> {
-    fn add(&mut self, x: T) -> Option<T>;
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::Hash;
+use core::ops::RangeBounds;
+
+#[cfg(feature = "testkit")]
+pub mod testkit;
+
+/// 要素の追加・置き換えが拒否されたときのエラー
+///
+/// 拒否された要素を保持しているので、呼び出し側で取り戻せる
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListError<T> {
+    /// インデックスが範囲外
+    OutOfBounds { index: usize, size: usize, value: T },
+    /// 容量が足りない
+    CapacityExhausted(T),
+}
+
+impl<T> ListError<T> {
+    /// 拒否された要素を取り出す
+    pub fn into_inner(self) -> T {
+        match self {
+            ListError::OutOfBounds { value, .. } => value,
+            ListError::CapacityExhausted(value) => value,
+        }
+    }
+}
+
+impl<T> fmt::Display for ListError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListError::OutOfBounds { index, size, .. } => write!(
+                f,
+                "index out of bounds: the size is {size} but the index is {index}"
+            ),
+            ListError::CapacityExhausted(_) => write!(f, "capacity exhausted"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for ListError<T> {}
+
+/// すべてのコンテナに共通の操作
+pub trait Collection {
+    /// 要素数を返す
+    fn size(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+    /// すべての要素を取り除く
+    fn clear(&mut self);
+}
+
+/// 配列の確保量を扱えるコンテナ
+///
+/// 確保量は要素数に応じて伸縮するので、`reserve`した領域も要素を取り除くと解放されることがある
+pub trait Capacity: Collection {
+    /// 再確保せずに保持できる要素数を返す
+    fn capacity(&self) -> usize;
+    /// 少なくとも`additional`個の要素を再確保せずに追加できるようにする
+    fn reserve(&mut self, additional: usize);
+    /// 確保量を要素数に合わせて縮める
+    fn shrink_to_fit(&mut self);
+}
+
+/// キュー
+///
+/// `remove`はFIFOやランダムなど実装ごとの規則で次の要素を取り出す。LIFOには`Stack`を使う
+pub trait Queue<T>: Collection {
+    fn add(&mut self, x: T) -> Result<(), ListError<T>>;
     fn remove(&mut self) -> Option<T>;
+    /// 次の`remove`で取り出される要素を返す
+    fn peek(&self) -> Option<&T>;
 }
 
-pub trait Deque<T> {
-    fn add_front(&mut self, x: T) -> Option<T>;
+/// 後入れ先出し(LIFO)のスタック
+///
+/// 空のときは`pop`、`peek`、`peek_mut`のいずれも`None`を返す
+pub trait Stack<T>: Collection {
+    fn push(&mut self, x: T) -> Result<(), ListError<T>>;
+    /// 最後に`push`した要素を取り除いて返す
+    fn pop(&mut self) -> Option<T>;
+    /// 最後に`push`した要素を返す
+    fn peek(&self) -> Option<&T>;
+    fn peek_mut(&mut self) -> Option<&mut T>;
+}
+
+pub trait Deque<T>: Collection {
+    fn add_front(&mut self, x: T) -> Result<(), ListError<T>>;
     fn remove_front(&mut self) -> Option<T>;
-    fn add_back(&mut self, x: T) -> Option<T>;
+    fn add_back(&mut self, x: T) -> Result<(), ListError<T>>;
     fn remove_back(&mut self) -> Option<T>;
+    fn front(&self) -> Option<&T>;
+    fn back(&self) -> Option<&T>;
+    fn front_mut(&mut self) -> Option<&mut T>;
+    fn back_mut(&mut self) -> Option<&mut T>;
 }
 
-pub trait List<T> {
-    fn size(&self) -> usize;
+pub trait List<T>: Collection {
     fn get(&self, i: usize) -> Option<&T>;
-    fn set(&mut self, i: usize, x: T) -> Option<T>;
-    fn add(&mut self, i: usize, x: T) -> Option<T>;
+    /// `i`番目の要素を`x`に置き換え、元の要素を返す
+    fn set(&mut self, i: usize, x: T) -> Result<T, ListError<T>>;
+    /// `i`番目に`x`を挿入する。`i`は`0..=size()`の範囲
+    fn add(&mut self, i: usize, x: T) -> Result<(), ListError<T>>;
     fn remove(&mut self, i: usize) -> Option<T>;
 }
 
-pub trait USet<T> {
-    fn size(&self) -> usize;
-    fn add(&mut self, x: T) -> Option<T>;
-    fn remove(&mut self, x: T) -> Option<T>;
-    fn find(&self, x: T) -> &Option<T>;
+/// 最小の要素から順に取り出せるキュー
+pub trait PriorityQueue<T: Ord>: Collection {
+    fn add(&mut self, x: T) -> Result<(), ListError<T>>;
+    /// 最小の要素を取り除いて返す
+    fn remove_min(&mut self) -> Option<T>;
+    /// 最小の要素を返す
+    fn peek_min(&self) -> Option<&T>;
+}
+
+/// 順序を持たない集合
+///
+/// 探索には`T`が借用できる型`Q`を使える(例えば`String`の集合を`&str`で探せる)
+pub trait USet<T>: Collection {
+    /// `x`を追加する。等しい要素が既にあれば追加せず`false`を返す
+    fn add(&mut self, x: T) -> bool;
+    /// `x`と等しい要素を取り除いて返す
+    fn remove<Q>(&mut self, x: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
+    /// `x`と等しい要素を返す
+    fn find<Q>(&self, x: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
+    fn contains<Q>(&self, x: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(x).is_some()
+    }
+}
+
+/// キーと値の組を保持する、順序を持たない写像
+pub trait UMap<K, V>: Collection {
+    /// `k`に`v`を対応付ける。既に値があれば置き換えて元の値を返す
+    fn insert(&mut self, k: K, v: V) -> Option<V>;
+    /// `k`に対応する値を取り除いて返す
+    fn remove<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
+    fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
+    fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
+    fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(k).is_some()
+    }
+}
+
+/// 要素が全順序で並んだ集合
+pub trait SSet<T: Ord>: Collection {
+    /// `x`を追加する。等しい要素が既にあれば追加せず`false`を返す
+    fn add(&mut self, x: T) -> bool;
+    /// `x`と等しい要素を取り除いて返す
+    fn remove(&mut self, x: &T) -> Option<T>;
+    /// `x`以上の最小の要素を返す
+    fn find(&self, x: &T) -> Option<&T>;
+    /// `x`未満の最大の要素を返す
+    fn predecessor(&self, x: &T) -> Option<&T>;
+    fn min(&self) -> Option<&T>;
+    fn max(&self) -> Option<&T>;
+    /// `range`に含まれる要素を昇順に返す
+    fn range<'a, R>(&'a self, range: R) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a,
+        R: RangeBounds<T>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// トレイトの使い勝手を確かめるための線形探索による実装
+    struct VecSet<T>(Vec<T>);
+
+    impl<T> Collection for VecSet<T> {
+        fn size(&self) -> usize {
+            self.0.len()
+        }
+
+        fn clear(&mut self) {
+            self.0.clear();
+        }
+    }
+
+    impl<T: Eq> USet<T> for VecSet<T> {
+        fn add(&mut self, x: T) -> bool {
+            if self.0.contains(&x) {
+                return false;
+            }
+            self.0.push(x);
+            true
+        }
+
+        fn remove<Q>(&mut self, x: &Q) -> Option<T>
+        where
+            T: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+        {
+            let i = self.0.iter().position(|y| y.borrow() == x)?;
+            Some(self.0.swap_remove(i))
+        }
+
+        fn find<Q>(&self, x: &Q) -> Option<&T>
+        where
+            T: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+        {
+            self.0.iter().find(|y| (*y).borrow() == x)
+        }
+    }
+
+    struct VecMap<K, V>(Vec<(K, V)>);
+
+    impl<K, V> Collection for VecMap<K, V> {
+        fn size(&self) -> usize {
+            self.0.len()
+        }
+
+        fn clear(&mut self) {
+            self.0.clear();
+        }
+    }
+
+    impl<K: Eq, V> UMap<K, V> for VecMap<K, V> {
+        fn insert(&mut self, k: K, v: V) -> Option<V> {
+            match self.0.iter_mut().find(|(l, _)| *l == k) {
+                Some((_, w)) => Some(core::mem::replace(w, v)),
+                None => {
+                    self.0.push((k, v));
+                    None
+                }
+            }
+        }
+
+        fn remove<Q>(&mut self, k: &Q) -> Option<V>
+        where
+            K: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+        {
+            let i = self.0.iter().position(|(l, _)| l.borrow() == k)?;
+            Some(self.0.swap_remove(i).1)
+        }
+
+        fn get<Q>(&self, k: &Q) -> Option<&V>
+        where
+            K: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+        {
+            self.0.iter().find(|(l, _)| l.borrow() == k).map(|(_, v)| v)
+        }
+
+        fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+        where
+            K: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+        {
+            self.0
+                .iter_mut()
+                .find(|(l, _)| l.borrow() == k)
+                .map(|(_, v)| v)
+        }
+    }
+
+    #[test]
+    fn uset_borrowed_lookup() {
+        let mut s = VecSet(Vec::new());
+        assert!(s.add("foo".to_string()));
+        assert!(s.add("bar".to_string()));
+        assert!(!s.add("foo".to_string()));
+
+        // `String`の集合を`&str`で探せる
+        assert_eq!(s.find("foo"), Some(&"foo".to_string()));
+        assert!(s.contains("bar"));
+        assert!(!s.contains("baz"));
+        assert_eq!(s.remove("foo"), Some("foo".to_string()));
+        assert_eq!(s.remove("foo"), None);
+        assert_eq!(s.size(), 1);
+    }
+
+    #[test]
+    fn umap_borrowed_lookup() {
+        let mut m = VecMap(Vec::new());
+        assert_eq!(m.insert("a".to_string(), 1), None);
+        assert_eq!(m.insert("b".to_string(), 2), None);
+        assert_eq!(m.insert("a".to_string(), 3), Some(1));
+
+        assert_eq!(m.get("a"), Some(&3));
+        if let Some(v) = m.get_mut("b") {
+            *v += 10;
+        }
+        assert_eq!(m.get("b"), Some(&12));
+        assert!(m.contains_key("b"));
+        assert_eq!(m.remove("a"), Some(3));
+        assert!(!m.contains_key("a"));
+        assert_eq!(m.size(), 1);
+    }
 }
@@ -0,0 +1,352 @@
+//! `List`・`Queue`・`Stack`・`Deque`の実装が仕様どおりに振る舞うかを確かめるテスト用の関数
+//!
+//! 空のコンテナにランダムな操作列を適用し、同じ操作を`Vec`や`VecDeque`にも適用して結果を比べる。
+//! 操作列は`seed`だけで決まるので、失敗したときはメッセージの`seed`と`step`で再現できる。
+//! 各関数の`invariants`は操作のたびに呼ばれるので、実装の内部の整合性を確かめる関数を渡せる
+
+use std::collections::VecDeque;
+
+use crate::{Deque, List, ListError, Queue, Stack};
+
+/// 1つの`seed`で適用する操作の数
+pub const STEPS: usize = 2000;
+
+/// 操作列を作る乱数
+///
+/// 要素を増やす操作と減らす操作の比率を一定の間隔で入れ替え、配列の伸長と縮小の両方を起こす
+struct Ops {
+    rng: fastrand::Rng,
+    /// 次に追加する要素。値がすべて異なるので、取り違えると比較で分かる
+    next: u32,
+}
+
+impl Ops {
+    fn new(seed: u64) -> Self {
+        Self {
+            rng: fastrand::Rng::with_seed(seed),
+            next: 0,
+        }
+    }
+
+    /// 要素を増やす操作を選ぶなら`true`を返す
+    fn grow(&mut self, step: usize) -> bool {
+        let percent = if (step / 300).is_multiple_of(2) {
+            65
+        } else {
+            35
+        };
+        self.rng.u32(0..100) < percent
+    }
+
+    /// まれに`true`を返す。`clear`などの大きな操作に使う
+    fn rare(&mut self) -> bool {
+        self.rng.u32(0..500) == 0
+    }
+
+    /// `0..=n`に加えて範囲外の`n + 1`も選ぶ
+    fn index(&mut self, n: usize) -> usize {
+        self.rng.usize(0..n + 2)
+    }
+
+    fn value(&mut self) -> u32 {
+        self.next += 1;
+        self.next
+    }
+}
+
+/// `List`の実装を`Vec`と比べる
+///
+/// `list`は空でなければならない。範囲外のインデックスに対するエラーも比べる
+pub fn check_list<L: List<u32>>(mut list: L, seed: u64, invariants: impl Fn(&L)) {
+    assert!(list.is_empty(), "the list must be empty");
+    let mut model = Vec::new();
+    let mut ops = Ops::new(seed);
+    for step in 0..STEPS {
+        let n = model.len();
+        let i = ops.index(n);
+        let at = format!("seed {seed}, step {step}");
+        if ops.rare() {
+            list.clear();
+            model.clear();
+        } else if ops.grow(step) {
+            let x = ops.value();
+            let expected = if i <= n {
+                model.insert(i, x);
+                Ok(())
+            } else {
+                Err(ListError::OutOfBounds {
+                    index: i,
+                    size: n,
+                    value: x,
+                })
+            };
+            assert_eq!(list.add(i, x), expected, "{at}: add({i}, {x})");
+        } else if ops.rng.bool() {
+            let expected = (i < n).then(|| model.remove(i));
+            assert_eq!(list.remove(i), expected, "{at}: remove({i})");
+        } else {
+            let x = ops.value();
+            let expected = match model.get_mut(i) {
+                Some(y) => Ok(core::mem::replace(y, x)),
+                None => Err(ListError::OutOfBounds {
+                    index: i,
+                    size: n,
+                    value: x,
+                }),
+            };
+            assert_eq!(list.set(i, x), expected, "{at}: set({i}, {x})");
+        }
+
+        assert_eq!(list.size(), model.len(), "{at}: size");
+        invariants(&list);
+        assert_eq!(list.get(model.len()), None, "{at}: get({})", model.len());
+        for (i, x) in model.iter().enumerate() {
+            assert_eq!(list.get(i), Some(x), "{at}: get({i})");
+        }
+    }
+}
+
+/// `Stack`の実装を`Vec`と比べる
+pub fn check_stack<S: Stack<u32>>(mut stack: S, seed: u64, invariants: impl Fn(&S)) {
+    assert!(stack.is_empty(), "the stack must be empty");
+    let mut model = Vec::new();
+    let mut ops = Ops::new(seed);
+    for step in 0..STEPS {
+        let at = format!("seed {seed}, step {step}");
+        if ops.rare() {
+            stack.clear();
+            model.clear();
+        } else if ops.grow(step) {
+            let x = ops.value();
+            model.push(x);
+            assert_eq!(stack.push(x), Ok(()), "{at}: push({x})");
+        } else if ops.rng.bool() {
+            assert_eq!(stack.pop(), model.pop(), "{at}: pop()");
+        } else {
+            let x = ops.value();
+            if let Some(y) = stack.peek_mut() {
+                *y = x;
+            }
+            if let Some(y) = model.last_mut() {
+                *y = x;
+            }
+        }
+
+        assert_eq!(stack.size(), model.len(), "{at}: size");
+        invariants(&stack);
+        assert_eq!(Stack::peek(&stack), model.last(), "{at}: peek()");
+    }
+
+    // 残りの要素を取り出して順序を確かめる
+    while let Some(x) = model.pop() {
+        assert_eq!(stack.pop(), Some(x), "seed {seed}: pop() at the end");
+        invariants(&stack);
+    }
+    assert_eq!(stack.pop(), None, "seed {seed}: pop() on empty");
+}
+
+/// 先入れ先出しの`Queue`の実装を`VecDeque`と比べる
+pub fn check_fifo<Q: Queue<u32>>(mut queue: Q, seed: u64, invariants: impl Fn(&Q)) {
+    assert!(queue.is_empty(), "the queue must be empty");
+    let mut model = VecDeque::new();
+    let mut ops = Ops::new(seed);
+    for step in 0..STEPS {
+        let at = format!("seed {seed}, step {step}");
+        if ops.rare() {
+            queue.clear();
+            model.clear();
+        } else if ops.grow(step) {
+            let x = ops.value();
+            model.push_back(x);
+            assert_eq!(queue.add(x), Ok(()), "{at}: add({x})");
+        } else {
+            assert_eq!(queue.remove(), model.pop_front(), "{at}: remove()");
+        }
+
+        assert_eq!(queue.size(), model.len(), "{at}: size");
+        invariants(&queue);
+        assert_eq!(Queue::peek(&queue), model.front(), "{at}: peek()");
+    }
+
+    while let Some(x) = model.pop_front() {
+        assert_eq!(queue.remove(), Some(x), "seed {seed}: remove() at the end");
+        invariants(&queue);
+    }
+    assert_eq!(queue.remove(), None, "seed {seed}: remove() on empty");
+}
+
+/// 取り出す順序を問わない`Queue`の実装を確かめる
+///
+/// `peek`した要素が次に取り出され、追加した要素がちょうど1回ずつ取り出されることだけを確かめる
+pub fn check_queue<Q: Queue<u32>>(mut queue: Q, seed: u64, invariants: impl Fn(&Q)) {
+    assert!(queue.is_empty(), "the queue must be empty");
+    // 取り出されていない要素の集合
+    let mut model = Vec::new();
+    let mut ops = Ops::new(seed);
+    let remove = |queue: &mut Q, model: &mut Vec<u32>, at: &str| {
+        let peeked = Queue::peek(queue).copied();
+        let x = queue.remove();
+        assert_eq!(x, peeked, "{at}: remove() differs from peek()");
+        if let Some(x) = x {
+            let i = model.iter().position(|&y| y == x);
+            let i = i.unwrap_or_else(|| panic!("{at}: remove() returned unknown {x}"));
+            model.swap_remove(i);
+        }
+    };
+    for step in 0..STEPS {
+        let at = format!("seed {seed}, step {step}");
+        if ops.rare() {
+            queue.clear();
+            model.clear();
+        } else if ops.grow(step) {
+            let x = ops.value();
+            model.push(x);
+            assert_eq!(queue.add(x), Ok(()), "{at}: add({x})");
+        } else {
+            remove(&mut queue, &mut model, &at);
+        }
+
+        assert_eq!(queue.size(), model.len(), "{at}: size");
+        invariants(&queue);
+    }
+
+    while !model.is_empty() {
+        remove(&mut queue, &mut model, &format!("seed {seed}, at the end"));
+        invariants(&queue);
+    }
+    assert_eq!(queue.remove(), None, "seed {seed}: remove() on empty");
+}
+
+/// `Deque`の実装を`VecDeque`と比べる
+pub fn check_deque<D: Deque<u32>>(mut deque: D, seed: u64, invariants: impl Fn(&D)) {
+    assert!(deque.is_empty(), "the deque must be empty");
+    let mut model = VecDeque::new();
+    let mut ops = Ops::new(seed);
+    for step in 0..STEPS {
+        let at = format!("seed {seed}, step {step}");
+        let front = ops.rng.bool();
+        if ops.rare() {
+            deque.clear();
+            model.clear();
+        } else if ops.grow(step) {
+            let x = ops.value();
+            if front {
+                model.push_front(x);
+                assert_eq!(deque.add_front(x), Ok(()), "{at}: add_front({x})");
+            } else {
+                model.push_back(x);
+                assert_eq!(deque.add_back(x), Ok(()), "{at}: add_back({x})");
+            }
+        } else if ops.rng.u32(0..4) != 0 {
+            if front {
+                let expected = model.pop_front();
+                assert_eq!(deque.remove_front(), expected, "{at}: remove_front()");
+            } else {
+                let expected = model.pop_back();
+                assert_eq!(deque.remove_back(), expected, "{at}: remove_back()");
+            }
+        } else {
+            let x = ops.value();
+            let (y, z) = if front {
+                (deque.front_mut(), model.front_mut())
+            } else {
+                (deque.back_mut(), model.back_mut())
+            };
+            assert_eq!(y.is_some(), z.is_some(), "{at}: front_mut() or back_mut()");
+            if let (Some(y), Some(z)) = (y, z) {
+                *y = x;
+                *z = x;
+            }
+        }
+
+        assert_eq!(deque.size(), model.len(), "{at}: size");
+        invariants(&deque);
+        assert_eq!(deque.front(), model.front(), "{at}: front()");
+        assert_eq!(deque.back(), model.back(), "{at}: back()");
+    }
+
+    while let Some(x) = model.pop_front() {
+        assert_eq!(
+            deque.remove_front(),
+            Some(x),
+            "seed {seed}: remove_front() at the end"
+        );
+        invariants(&deque);
+    }
+    assert_eq!(
+        deque.remove_back(),
+        None,
+        "seed {seed}: remove_back() on empty"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Collection;
+
+    /// `Vec`による正しい実装
+    struct VecList(Vec<u32>, bool);
+
+    impl Collection for VecList {
+        fn size(&self) -> usize {
+            self.0.len()
+        }
+
+        fn clear(&mut self) {
+            self.0.clear();
+        }
+    }
+
+    impl List<u32> for VecList {
+        fn get(&self, i: usize) -> Option<&u32> {
+            self.0.get(i)
+        }
+
+        fn set(&mut self, i: usize, x: u32) -> Result<u32, ListError<u32>> {
+            match self.0.get_mut(i) {
+                Some(y) => Ok(core::mem::replace(y, x)),
+                None => Err(ListError::OutOfBounds {
+                    index: i,
+                    size: self.0.len(),
+                    value: x,
+                }),
+            }
+        }
+
+        fn add(&mut self, i: usize, x: u32) -> Result<(), ListError<u32>> {
+            if i > self.0.len() {
+                return Err(ListError::OutOfBounds {
+                    index: i,
+                    size: self.0.len(),
+                    value: x,
+                });
+            }
+            // 2つめのフィールドが`true`なら、末尾以外への挿入で1つずれる誤った実装にする
+            let i = if self.1 && i > 0 && i < self.0.len() {
+                i - 1
+            } else {
+                i
+            };
+            self.0.insert(i, x);
+            Ok(())
+        }
+
+        fn remove(&mut self, i: usize) -> Option<u32> {
+            (i < self.0.len()).then(|| self.0.remove(i))
+        }
+    }
+
+    #[test]
+    fn list() {
+        for seed in 0..4 {
+            check_list(VecList(Vec::new(), false), seed, |_| {});
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "seed 0, step")]
+    fn list_bug() {
+        check_list(VecList(Vec::new(), true), 0, |_| {});
+    }
+}
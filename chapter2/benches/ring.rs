@@ -0,0 +1,47 @@
+//! 環状配列の位置計算(剰余とマスク)による差を測るベンチマーク
+use std::hint::black_box;
+
+use chapter1::{Collection, Deque, List, Queue};
+use chapter2::{arraydeque::ArrayDeque, arrayqueue::ArrayQueue};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+const N: u64 = 10_000;
+
+fn ring(c: &mut Criterion) {
+    let mut g = c.benchmark_group("ArrayDeque");
+    // 先頭側にも追加して、配列の末尾で折り返した状態にする
+    let mut a = ArrayDeque::with_capacity(0);
+    for x in 0..N {
+        if x % 2 == 0 {
+            a.add_front(x).unwrap();
+        } else {
+            a.add_back(x).unwrap();
+        }
+    }
+    g.bench_function("get", |b| {
+        b.iter(|| (0..a.size()).map(|i| a[black_box(i)]).sum::<u64>())
+    });
+    g.bench_function("set", |b| {
+        b.iter(|| {
+            for i in 0..a.size() {
+                a.set(black_box(i), i as u64).unwrap();
+            }
+        })
+    });
+    g.finish();
+
+    let mut g = c.benchmark_group("ArrayQueue");
+    g.bench_function("add_remove", |b| {
+        let mut q = (0..N).collect::<ArrayQueue<_>>();
+        b.iter(|| {
+            for x in 0..N {
+                q.add(black_box(x)).unwrap();
+                black_box(q.remove());
+            }
+        })
+    });
+    g.finish();
+}
+
+criterion_group!(benches, ring);
+criterion_main!(benches);
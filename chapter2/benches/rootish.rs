@@ -0,0 +1,46 @@
+//! `RootishArrayStack`の要素のずらし方とブロックの解放方針による差を測るベンチマーク
+use std::hint::black_box;
+
+use chapter1::{Collection, List, Stack};
+use chapter2::rootisharraystack::RootishArrayStack;
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+
+const N: usize = 10_000;
+/// 1回のベンチマークで挿入・削除する回数
+const OPS: usize = 1_000;
+
+fn rootish(c: &mut Criterion) {
+    let mut g = c.benchmark_group("RootishArrayStack");
+    g.bench_function("insert_remove_middle", |b| {
+        b.iter_batched_ref(
+            || (0..N).collect::<RootishArrayStack<_>>(),
+            |a| {
+                for x in 0..OPS {
+                    let i = a.size() / 2;
+                    a.add(black_box(i), x).unwrap();
+                }
+                for _ in 0..OPS {
+                    let i = a.size() / 2;
+                    black_box(a.remove(black_box(i)));
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    // 要素数がちょうどブロックの境界にある状態で追加と削除を繰り返す
+    // 1+2+...+140 = 9870
+    let boundary = 140 * 141 / 2;
+    g.bench_function("push_pop_boundary", |b| {
+        let mut a = (0..boundary).collect::<RootishArrayStack<_>>();
+        b.iter(|| {
+            for x in 0..OPS {
+                a.push(black_box(x)).unwrap();
+                black_box(a.pop());
+            }
+        })
+    });
+    g.finish();
+}
+
+criterion_group!(benches, rootish);
+criterion_main!(benches);
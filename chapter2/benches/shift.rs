@@ -0,0 +1,111 @@
+//! 要素をずらす`add`/`remove`を`Vec`、`VecDeque`と比べるベンチマーク
+use std::collections::VecDeque;
+use std::hint::black_box;
+
+use chapter1::{Collection, List};
+use chapter2::{arraydeque::ArrayDeque, arraystack::ArrayStack};
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+
+const N: u64 = 10_000;
+/// 1回のベンチマークで挿入・削除する回数
+const OPS: usize = 1_000;
+
+fn array_stack(c: &mut Criterion) {
+    let mut g = c.benchmark_group("insert_remove_middle");
+    g.bench_function("ArrayStack", |b| {
+        b.iter_batched_ref(
+            || (0..N).collect::<ArrayStack<_>>(),
+            |a| {
+                for x in 0..OPS as u64 {
+                    let i = a.size() / 2;
+                    a.add(black_box(i), x).unwrap();
+                }
+                for _ in 0..OPS {
+                    let i = a.size() / 2;
+                    black_box(a.remove(black_box(i)));
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    g.bench_function("Vec", |b| {
+        b.iter_batched_ref(
+            || (0..N).collect::<Vec<_>>(),
+            |a| {
+                for x in 0..OPS as u64 {
+                    let i = a.len() / 2;
+                    a.insert(black_box(i), x);
+                }
+                for _ in 0..OPS {
+                    let i = a.len() / 2;
+                    black_box(a.remove(black_box(i)));
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    g.bench_function("ArrayDeque", |b| {
+        b.iter_batched_ref(
+            || (0..N).collect::<ArrayDeque<_>>(),
+            |a| {
+                for x in 0..OPS as u64 {
+                    let i = a.size() / 3;
+                    a.add(black_box(i), x).unwrap();
+                }
+                for _ in 0..OPS {
+                    let i = a.size() / 3;
+                    black_box(a.remove(black_box(i)));
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    g.bench_function("VecDeque", |b| {
+        b.iter_batched_ref(
+            || (0..N).collect::<VecDeque<_>>(),
+            |a| {
+                for x in 0..OPS as u64 {
+                    let i = a.len() / 3;
+                    a.insert(black_box(i), x);
+                }
+                for _ in 0..OPS {
+                    let i = a.len() / 3;
+                    black_box(a.remove(black_box(i)));
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    g.finish();
+
+    let mut g = c.benchmark_group("add_all");
+    let xs = (0..N).collect::<Vec<_>>();
+    g.bench_function("ArrayStack", |b| {
+        b.iter_batched_ref(
+            || (0..N).collect::<ArrayStack<_>>(),
+            |a| {
+                for k in 0..10 {
+                    a.add_all(black_box(k * 100), xs[..100].iter().copied())
+                        .unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    g.bench_function("Vec", |b| {
+        b.iter_batched_ref(
+            || (0..N).collect::<Vec<_>>(),
+            |a| {
+                for k in 0..10 {
+                    let i = black_box(k * 100);
+                    a.splice(i..i, xs[..100].iter().copied());
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    g.finish();
+}
+
+criterion_group!(benches, array_stack);
+criterion_main!(benches);
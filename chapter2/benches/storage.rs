@@ -0,0 +1,100 @@
+//! `Array`の格納方式による差を測るベンチマーク
+use std::hint::black_box;
+
+use chapter1::{Collection, Deque, Stack};
+use chapter2::{
+    arraydeque::ArrayDeque, arraystack::ArrayStack, rootisharraystack::RootishArrayStack,
+};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+const N: u64 = 10_000;
+const LARGE_N: u64 = 1 << 20;
+
+fn array_stack(c: &mut Criterion) {
+    let mut g = c.benchmark_group("ArrayStack");
+    g.bench_function("push", |b| {
+        b.iter(|| {
+            let mut a = ArrayStack::with_capacity(0);
+            for x in 0..N {
+                a.push(black_box(x)).unwrap();
+            }
+            a
+        })
+    });
+    let a = (0..N).collect::<ArrayStack<_>>();
+    g.bench_function("get", |b| {
+        b.iter(|| (0..a.size()).map(|i| a[black_box(i)]).sum::<u64>())
+    });
+    g.bench_function("push_pop", |b| {
+        b.iter(|| {
+            let mut a = ArrayStack::with_capacity(0);
+            for x in 0..N {
+                a.push(black_box(x)).unwrap();
+            }
+            while let Some(x) = a.pop() {
+                black_box(x);
+            }
+        })
+    });
+    g.finish();
+}
+
+fn array_deque(c: &mut Criterion) {
+    let mut g = c.benchmark_group("ArrayDeque");
+    g.bench_function("add_front_back", |b| {
+        b.iter(|| {
+            let mut a = ArrayDeque::with_capacity(0);
+            for x in 0..N / 2 {
+                a.add_front(black_box(x)).unwrap();
+                a.add_back(black_box(x)).unwrap();
+            }
+            a
+        })
+    });
+    let a = (0..N).collect::<ArrayDeque<_>>();
+    g.bench_function("get", |b| {
+        b.iter(|| (0..a.size()).map(|i| a[black_box(i)]).sum::<u64>())
+    });
+    g.bench_function("remove_front", |b| {
+        b.iter_batched(
+            || (0..N).collect::<ArrayDeque<_>>(),
+            |mut a| {
+                while let Some(x) = a.remove_front() {
+                    black_box(x);
+                }
+            },
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    g.finish();
+}
+
+fn rootish_array_stack(c: &mut Criterion) {
+    let mut g = c.benchmark_group("RootishArrayStack");
+    g.bench_function("push", |b| {
+        b.iter(|| {
+            let mut a = RootishArrayStack::with_capacity();
+            for x in 0..N {
+                a.push(black_box(x)).unwrap();
+            }
+            a
+        })
+    });
+    let a = (0..N).collect::<RootishArrayStack<_>>();
+    g.bench_function("get", |b| {
+        b.iter(|| (0..a.size()).map(|i| a[black_box(i)]).sum::<u64>())
+    });
+    // 末尾のN個だけを読み、ブロックのインデックスが大きいときの`get`を測る
+    let a = (0..LARGE_N).collect::<RootishArrayStack<_>>();
+    g.bench_function("get_large", |b| {
+        b.iter(|| {
+            (LARGE_N - N..LARGE_N)
+                .map(|i| a[black_box(i as usize)])
+                .sum::<u64>()
+        })
+    });
+    g.finish();
+}
+
+criterion_group!(benches, array_stack, array_deque, rootish_array_stack);
+criterion_main!(benches);
@@ -1,7 +1,15 @@
-use chapter1::{Deque, List};
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::{Index, IndexMut, Range};
 
-use crate::Array;
+use chapter1::{Capacity, Collection, Deque, List, ListError, Stack};
 
+use crate::{Array, index_out_of_bounds};
+
+/// `a`を環状配列として使い、`j`から始まる`n`個が初期化済みの要素
+///
+/// 配列長は常に2の冪にして、位置の折り返しを剰余ではなくマスクで計算する
 pub struct ArrayDeque<T> {
     a: Array<T>,
     j: usize,
@@ -9,76 +17,248 @@ pub struct ArrayDeque<T> {
 }
 
 impl<T> ArrayDeque<T> {
+    /// 配列長は`len`以上の2の冪に切り上げる
     pub fn with_capacity(len: usize) -> Self {
         ArrayDeque {
-            a: Array::with_capacity(len),
+            a: Array::with_capacity(len.next_power_of_two()),
             j: 0,
             n: 0,
         }
     }
 
     fn resize(&mut self) {
-        let mut new_a = Array::with_capacity((self.size() * 2).max(1));
+        // 配列には空きを1つ残すので`n + 1`個分の2倍にする。
+        // `2n`では2の冪への切り上げで配列長が変わらず、空きがなくなることがある
+        self.reallocate((self.size() + 1) * 2);
+    }
+
+    /// 長さ`len`以上の2の冪の配列を確保して、要素を先頭から詰めて移す
+    ///
+    /// 配列長が変わらなければ何もしない
+    fn reallocate(&mut self, len: usize) {
+        let len = len.next_power_of_two();
+        if len != self.a.length() {
+            self.a = reallocate_ring(&mut self.a, self.j, self.n, len);
+            self.j = 0;
+        }
+    }
+
+    /// `k`を配列内の位置に折り返す
+    fn wrap(&self, k: usize) -> usize {
+        wrap_index(self.a.length(), k)
+    }
+
+    /// 環状配列上で`src`から始まる`len`個の要素を`dest`から始まる位置へ移す
+    ///
+    /// 区間は重なってもよい。折り返さない連続した区間(高々3つ)に分けて、それぞれまとめて移す
+    fn wrap_copy(&mut self, src: usize, dest: usize, len: usize) {
         let a_size = self.a.length();
-        for k in 0..self.n {
-            core::mem::swap(&mut self.a[(self.j + k) % a_size], &mut new_a[k]);
+        if self.wrap(dest + a_size - src) < len {
+            // 後ろへずらすときは、移し終えていない要素を上書きしないよう後ろから移す
+            let mut k = len;
+            while k > 0 {
+                let src_end = self.wrap(src + k - 1) + 1;
+                let dest_end = self.wrap(dest + k - 1) + 1;
+                let run = k.min(src_end).min(dest_end);
+                self.a.copy_within(src_end - run..src_end, dest_end - run);
+                k -= run;
+            }
+        } else {
+            let mut k = 0;
+            while k < len {
+                let s = self.wrap(src + k);
+                let d = self.wrap(dest + k);
+                let run = (len - k).min(a_size - s).min(a_size - d);
+                self.a.copy_within(s..s + run, d);
+                k += run;
+            }
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        unsafe { Iter::new(&self.a, self.j, self.n) }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        unsafe { IterMut::new(&mut self.a, self.j, self.n) }
+    }
+
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        if i < self.n {
+            let k = self.wrap(self.j + i);
+            Some(unsafe { self.a.get_mut(k) })
+        } else {
+            None
+        }
+    }
+
+    /// 要素を先頭から順に並べた2つのスライスを返す
+    ///
+    /// 配列の末尾で折り返していなければ2つ目は空になる
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (head, tail) = ring_ranges(self.a.length(), self.j, self.n);
+        unsafe { (self.a.slice(head), self.a.slice(tail)) }
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (head, tail) = ring_ranges(self.a.length(), self.j, self.n);
+        // 折り返した区間は必ず`j`より前にある
+        unsafe { self.a.split_slices_mut(head, tail) }
+    }
+
+    /// 配列を再確保せずに要素を連続した位置へ並べ直し、そのスライスを返す
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        let (head, tail) = ring_ranges(self.a.length(), self.j, self.n);
+        if !tail.is_empty() {
+            // 先頭側の区間を折り返した区間の直後へ詰めてから、全体を回転させる
+            self.a.copy_within(head, tail.end);
+            self.j = 0;
+            unsafe { self.a.slice_mut(0..self.n) }.rotate_left(tail.end);
+        }
+        unsafe { self.a.slice_mut(self.j..self.j + self.n) }
+    }
+
+    /// 先頭の`r`個を末尾へ移す。`r`と`n - r`の小さい方の数だけ要素を移す
+    ///
+    /// `r`が要素数より大きいとpanicする
+    pub fn rotate_left(&mut self, r: usize) {
+        assert!(r <= self.n, "rotation {r} exceeds the size {}", self.n);
+        if r <= self.n - r {
+            // 先頭のr個を末尾の後ろへ移す
+            self.wrap_copy(self.j, self.wrap(self.j + self.n), r);
+            self.j = self.wrap(self.j + r);
+        } else {
+            self.rotate_back(self.n - r);
+        }
+    }
+
+    /// 末尾の`r`個を先頭へ移す。`r`と`n - r`の小さい方の数だけ要素を移す
+    ///
+    /// `r`が要素数より大きいとpanicする
+    pub fn rotate_right(&mut self, r: usize) {
+        assert!(r <= self.n, "rotation {r} exceeds the size {}", self.n);
+        if r <= self.n - r {
+            self.rotate_back(r);
+        } else {
+            self.rotate_left(self.n - r);
         }
-        self.a = new_a;
-        self.j = 0;
+    }
+
+    /// 末尾の`r`個を先頭の前へ移す
+    fn rotate_back(&mut self, r: usize) {
+        let a_size = self.a.length();
+        let src = self.wrap(self.j + self.n - r);
+        let dest = self.wrap(self.j + a_size - r);
+        self.wrap_copy(src, dest, r);
+        self.j = dest;
+    }
+
+    /// 内部の状態が不変条件を満たすかを確かめ、満たさなければpanicする
+    ///
+    /// 配列長が2の冪で、`j`が配列内にあり、配列に空きが1つ以上残っていること。
+    /// 初期化済みの位置は`j`から折り返して`n`個
+    #[cfg(any(test, feature = "debug-invariants"))]
+    pub fn check_invariants(&self) {
+        let len = self.a.length();
+        assert!(len.is_power_of_two(), "array length {len}");
+        assert!(self.j < len, "j = {} >= {len}", self.j);
+        assert!(self.n < len, "n = {} >= {len}", self.n);
+        let (head, tail) = ring_ranges(len, self.j, self.n);
+        self.a
+            .check_live(|k| head.contains(&k) || tail.contains(&k));
     }
 }
 
-impl<T> List<T> for ArrayDeque<T> {
+impl<T> Drop for ArrayDeque<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Collection for ArrayDeque<T> {
     fn size(&self) -> usize {
         self.n
     }
 
+    fn clear(&mut self) {
+        // dropがpanicしても二重にdropしないよう、先に要素数を0にする
+        let n = core::mem::replace(&mut self.n, 0);
+        unsafe { drop_ring(&mut self.a, self.j, n) }
+        self.j = 0;
+    }
+}
+
+/// `add`は要素数+1が配列長に達すると伸長するので、保持できるのは配列長-1個まで
+impl<T> Capacity for ArrayDeque<T> {
+    fn capacity(&self) -> usize {
+        self.a.length().saturating_sub(1)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        if self.n + additional > self.capacity() {
+            self.reallocate(self.n + additional + 1);
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        if self.capacity() > self.n {
+            self.reallocate(self.n + 1);
+        }
+    }
+}
+
+impl<T> List<T> for ArrayDeque<T> {
     fn get(&self, i: usize) -> Option<&T> {
         if i < self.n {
-            let a_size = self.a.length();
-            self.a[(self.j + i) % a_size].as_ref()
+            Some(unsafe { self.a.get(self.wrap(self.j + i)) })
         } else {
             None
         }
     }
 
-    fn set(&mut self, i: usize, x: T) -> Option<T> {
+    fn set(&mut self, i: usize, x: T) -> Result<T, ListError<T>> {
         if i < self.n {
-            let a_size = self.a.length();
-            self.a[(self.j + i) % a_size].replace(x)
+            let k = self.wrap(self.j + i);
+            Ok(unsafe { self.a.replace(k, x) })
         } else {
-            None
+            Err(ListError::OutOfBounds {
+                index: i,
+                size: self.n,
+                value: x,
+            })
         }
     }
 
-    fn add(&mut self, i: usize, x: T) -> Option<T> {
+    fn add(&mut self, i: usize, x: T) -> Result<(), ListError<T>> {
+        // 範囲外
+        if i > self.size() {
+            return Err(ListError::OutOfBounds {
+                index: i,
+                size: self.n,
+                value: x,
+            });
+        }
+
         if self.size() + 1 >= self.a.length() {
             self.resize();
         }
 
-        // 範囲外
-        if i > self.size() {
-            return Some(x);
-        }
         let a_size = self.a.length();
         if i < self.size() / 2 {
             // 0..iを左へシフト
             // jが0の時、aの後端へ移動させる。そうでなければ左に1つずらす
-            self.j = if self.j == 0 { a_size - 1 } else { self.j - 1 };
-            for k in 0..i {
-                self.a
-                    .swap((self.j + k) % a_size, (self.j + k + 1) % a_size);
-            }
+            let j = self.j;
+            self.j = self.wrap(j + a_size - 1);
+            self.wrap_copy(j, self.j, i);
         } else {
             // i..nを右へシフト
-            for k in (i..self.n).rev() {
-                self.a
-                    .swap((self.j + k) % a_size, (self.j + k + 1) % a_size);
-            }
+            let start = self.wrap(self.j + i);
+            self.wrap_copy(start, self.wrap(start + 1), self.n - i);
         };
-        let y = self.a[(self.j + i) % a_size].replace(x);
+        let k = self.wrap(self.j + i);
+        self.a.write(k, x);
         self.n += 1;
-        y
+        Ok(())
     }
 
     fn remove(&mut self, i: usize) -> Option<T> {
@@ -86,33 +266,28 @@ impl<T> List<T> for ArrayDeque<T> {
         if i >= self.size() {
             return None;
         };
-        let a_size = self.a.length();
-        let x = self.a[(self.j + i) % a_size].take();
+        let x = unsafe { self.a.read(self.wrap(self.j + i)) };
         if i < self.size() / 2 {
             // 0..iを右へシフト
-            for k in (0..i).rev() {
-                self.a
-                    .swap((self.j + k) % a_size, (self.j + k + 1) % a_size);
-            }
+            let j = self.j;
             // jを1つ右へずらす
-            self.j = (self.j + 1) % a_size;
+            self.j = self.wrap(j + 1);
+            self.wrap_copy(j, self.j, i);
         } else {
-            // (i+1)..(n-1)を左へシフト
-            for k in i..(self.size() - 1) {
-                self.a
-                    .swap((self.j + k) % a_size, (self.j + k + 1) % a_size);
-            }
+            // (i+1)..nを左へシフト
+            let start = self.wrap(self.j + i + 1);
+            self.wrap_copy(start, self.wrap(self.j + i), self.n - i - 1);
         };
         self.n -= 1;
         if 3 * self.size() < self.a.length() {
             self.resize();
         }
-        x
+        Some(x)
     }
 }
 
 impl<T> Deque<T> for ArrayDeque<T> {
-    fn add_front(&mut self, x: T) -> Option<T> {
+    fn add_front(&mut self, x: T) -> Result<(), ListError<T>> {
         List::add(self, 0, x)
     }
 
@@ -120,7 +295,7 @@ impl<T> Deque<T> for ArrayDeque<T> {
         List::remove(self, 0)
     }
 
-    fn add_back(&mut self, x: T) -> Option<T> {
+    fn add_back(&mut self, x: T) -> Result<(), ListError<T>> {
         List::add(self, self.size(), x)
     }
 
@@ -131,6 +306,334 @@ impl<T> Deque<T> for ArrayDeque<T> {
             List::remove(self, self.size() - 1)
         }
     }
+
+    fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    fn back(&self) -> Option<&T> {
+        self.get(self.size().checked_sub(1)?)
+    }
+
+    fn front_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(0)
+    }
+
+    fn back_mut(&mut self) -> Option<&mut T> {
+        let i = self.size().checked_sub(1)?;
+        self.get_mut(i)
+    }
+}
+
+impl<T> Stack<T> for ArrayDeque<T> {
+    fn push(&mut self, x: T) -> Result<(), ListError<T>> {
+        self.add_back(x)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.remove_back()
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.back()
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut T> {
+        self.back_mut()
+    }
+}
+
+impl<T> Index<usize> for ArrayDeque<T> {
+    type Output = T;
+
+    #[track_caller]
+    fn index(&self, i: usize) -> &Self::Output {
+        match self.get(i) {
+            Some(x) => x,
+            None => index_out_of_bounds(i, self.size()),
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for ArrayDeque<T> {
+    #[track_caller]
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
+        let size = self.size();
+        match self.get_mut(i) {
+            Some(x) => x,
+            None => index_out_of_bounds(i, size),
+        }
+    }
+}
+
+impl<T> FromIterator<T> for ArrayDeque<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::with_capacity(0);
+        a.extend(iter);
+        a
+    }
+}
+
+impl<T> Extend<T> for ArrayDeque<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for x in iter {
+            let added = self.add_back(x);
+            debug_assert!(added.is_ok());
+        }
+    }
+}
+
+impl<T> IntoIterator for ArrayDeque<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let n = core::mem::replace(&mut self.n, 0);
+        let a = core::mem::replace(&mut self.a, Array::with_capacity(0));
+        let j = core::mem::replace(&mut self.j, 0);
+        unsafe { IntoIter::new(a, j, n) }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ArrayDeque<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut ArrayDeque<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: Clone> Clone for ArrayDeque<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArrayDeque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// 先頭の位置`j`や配列長によらず、要素の列が等しければ等しい
+impl<T: PartialEq> PartialEq for ArrayDeque<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for ArrayDeque<T> {}
+
+impl<T: Hash> Hash for ArrayDeque<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // `Vec`やスライスと同じく要素数を先に書き、区切り位置の違う列が衝突しないようにする
+        state.write_usize(self.size());
+        for x in self.iter() {
+            x.hash(state);
+        }
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for ArrayDeque<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for ArrayDeque<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T> Default for ArrayDeque<T> {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+/// 長さ`len`の環状配列で`j`から始まる`n`個の要素が占める区間を、
+/// `j`から配列末尾までの区間と、配列先頭に折り返した区間の2つに分けて返す
+pub(crate) fn ring_ranges(len: usize, j: usize, n: usize) -> (Range<usize>, Range<usize>) {
+    let head_end = (j + n).min(len);
+    (j..head_end, 0..(j + n - head_end))
+}
+
+/// 長さが2の冪の環状配列で、`k`を配列内の位置に折り返す
+pub(crate) fn wrap_index(len: usize, k: usize) -> usize {
+    debug_assert!(len.is_power_of_two());
+    k & (len - 1)
+}
+
+/// 長さ`len`の配列を確保し、環状配列`a`で`j`から始まる`n`個の要素を先頭から詰めて移す
+///
+/// 移した後、`a`の要素は未初期化として扱う
+pub(crate) fn reallocate_ring<T>(a: &mut Array<T>, j: usize, n: usize, len: usize) -> Array<T> {
+    let mut new_a = Array::with_capacity(len);
+    let (head, tail) = ring_ranges(a.length(), j, n);
+    let head_len = head.len();
+    a.copy_to(head, &mut new_a, 0);
+    a.copy_to(tail, &mut new_a, head_len);
+    new_a
+}
+
+/// 環状配列`a`で`j`から始まる`n`個の要素をdropする
+///
+/// # Safety
+/// `j`から始まる`n`個の要素がすべて初期化済みであること
+pub(crate) unsafe fn drop_ring<T>(a: &mut Array<T>, j: usize, n: usize) {
+    let (head, tail) = ring_ranges(a.length(), j, n);
+    unsafe {
+        a.drop_range(head);
+        a.drop_range(tail);
+    }
+}
+
+/// 要素への参照を先頭から順に返すイテレータ
+pub struct Iter<'a, T> {
+    head: core::slice::Iter<'a, T>,
+    tail: core::slice::Iter<'a, T>,
+}
+
+impl<'a, T> Iter<'a, T> {
+    /// # Safety
+    /// 環状配列`a`で`j`から始まる`n`個の要素がすべて初期化済みであること
+    pub(crate) unsafe fn new(a: &'a Array<T>, j: usize, n: usize) -> Self {
+        let (head, tail) = ring_ranges(a.length(), j, n);
+        unsafe {
+            Self {
+                head: a.slice(head).iter(),
+                tail: a.slice(tail).iter(),
+            }
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.head.next().or_else(|| self.tail.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.head.len() + self.tail.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.tail.next_back().or_else(|| self.head.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+/// 要素への可変参照を先頭から順に返すイテレータ
+pub struct IterMut<'a, T> {
+    head: core::slice::IterMut<'a, T>,
+    tail: core::slice::IterMut<'a, T>,
+}
+
+impl<'a, T> IterMut<'a, T> {
+    /// # Safety
+    /// 環状配列`a`で`j`から始まる`n`個の要素がすべて初期化済みであること
+    pub(crate) unsafe fn new(a: &'a mut Array<T>, j: usize, n: usize) -> Self {
+        let (head, tail) = ring_ranges(a.length(), j, n);
+        // 折り返した区間は必ず`j`より前にある
+        let (head, tail) = unsafe { a.split_slices_mut(head, tail) };
+        Self {
+            head: head.iter_mut(),
+            tail: tail.iter_mut(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.head.next().or_else(|| self.tail.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.head.len() + self.tail.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.tail.next_back().or_else(|| self.head.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+/// 要素を先頭から順に取り出すイテレータ
+pub struct IntoIter<T> {
+    a: Array<T>,
+    j: usize,
+    n: usize,
+}
+
+impl<T> IntoIter<T> {
+    /// # Safety
+    /// 環状配列`a`で`j`から始まる`n`個の要素がすべて初期化済みであること
+    pub(crate) unsafe fn new(a: Array<T>, j: usize, n: usize) -> Self {
+        Self { a, j, n }
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.n == 0 {
+            return None;
+        }
+        let x = unsafe { self.a.read(self.j) };
+        self.j = wrap_index(self.a.length(), self.j + 1);
+        self.n -= 1;
+        Some(x)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let k = wrap_index(self.a.length(), self.j + self.n);
+        Some(unsafe { self.a.read(k) })
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        let n = core::mem::replace(&mut self.n, 0);
+        unsafe { drop_ring(&mut self.a, self.j, n) }
+    }
 }
 
 #[cfg(test)]
@@ -142,17 +645,22 @@ mod tests {
         // Initialize
         let mut a = ArrayDeque::with_capacity(12);
         for (i, c) in "abcdefgh".chars().enumerate() {
-            a.add(i, c);
+            a.add(i, c).unwrap();
+            a.check_invariants();
         }
 
         // Remove
         let x = a.remove(2);
         assert_eq!(x, Some('c'));
+        a.check_invariants();
 
         // Add
-        a.add(4, 'x');
-        a.add(3, 'y');
-        a.add(3, 'z');
+        a.add(4, 'x').unwrap();
+        a.check_invariants();
+        a.add(3, 'y').unwrap();
+        a.check_invariants();
+        a.add(3, 'z').unwrap();
+        a.check_invariants();
 
         // Check
         // 図では`abdyzexfgh`になっているが、`add(3,'z')`のときなぜか4個動き'z'が4個めに入っている
@@ -162,4 +670,332 @@ mod tests {
             assert_eq!(x, Some(&c));
         }
     }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut a = ArrayDeque::with_capacity(0);
+        a.add_back('a').unwrap();
+        a.add_front('b').unwrap();
+
+        // 範囲外の要素はそのまま返される
+        assert_eq!(
+            a.add(3, 'c'),
+            Err(ListError::OutOfBounds {
+                index: 3,
+                size: 2,
+                value: 'c'
+            })
+        );
+        assert_eq!(a.set(2, 'd').map_err(ListError::into_inner), Err('d'));
+        assert_eq!(a.set(1, 'e'), Ok('a'));
+        assert_eq!(a.size(), 2);
+        assert_eq!(a.get(0), Some(&'b'));
+        assert_eq!(a.get(1), Some(&'e'));
+    }
+
+    #[test]
+    fn stack() {
+        let mut a = ArrayDeque::with_capacity(0);
+        assert_eq!(a.pop(), None);
+        assert_eq!(a.peek(), None);
+        assert_eq!(a.peek_mut(), None);
+
+        for c in "abc".chars() {
+            a.push(c).unwrap();
+            a.check_invariants();
+        }
+        assert_eq!(a.peek(), Some(&'c'));
+        if let Some(x) = a.peek_mut() {
+            *x = 'x';
+        }
+        for c in "xba".chars() {
+            assert_eq!(a.pop(), Some(c));
+            a.check_invariants();
+        }
+        assert_eq!(a.pop(), None);
+        assert_eq!(a.size(), 0);
+    }
+
+    #[test]
+    fn front_back() {
+        let mut a = ArrayDeque::with_capacity(0);
+        assert_eq!(a.front(), None);
+        assert_eq!(a.back_mut(), None);
+
+        a.add_back('b').unwrap();
+        a.add_front('a').unwrap();
+        a.add_back('c').unwrap();
+        assert_eq!(a.front(), Some(&'a'));
+        assert_eq!(a.back(), Some(&'c'));
+
+        if let Some(x) = a.front_mut() {
+            *x = 'x';
+        }
+        if let Some(x) = a.back_mut() {
+            *x = 'z';
+        }
+        assert_eq!(a.remove_front(), Some('x'));
+        assert_eq!(a.remove_back(), Some('z'));
+        assert_eq!(a.front(), a.back());
+    }
+
+    #[test]
+    fn capacity() {
+        let mut a = ArrayDeque::with_capacity(0);
+        a.reserve(10);
+        assert!(a.capacity() >= 10);
+        for c in "abcdefghij".chars() {
+            a.add_front(c).unwrap();
+        }
+        assert!(a.capacity() >= 10);
+
+        a.remove_back();
+        a.shrink_to_fit();
+        // 配列長は2の冪に切り上げられる
+        assert_eq!(a.capacity(), 15);
+        assert_eq!(a.front(), Some(&'j'));
+        assert_eq!(a.back(), Some(&'b'));
+
+        a.clear();
+        assert!(a.is_empty());
+        assert_eq!(a.front(), None);
+        a.add_back('z').unwrap();
+        assert_eq!(a.front(), Some(&'z'));
+
+        // 空の配列から1つずつ追加しても、容量が要素数を下回らない
+        let mut a = ArrayDeque::with_capacity(0);
+        for x in 0..20 {
+            a.add_back(x).unwrap();
+            assert!(a.capacity() >= a.size(), "{}", a.size());
+        }
+    }
+
+    /// 配列長16で、`j`が`offset`の位置から`n`個を並べた`ArrayDeque`と、同じ列の`VecDeque`を返す
+    ///
+    /// 要素数を保ったまま先頭を末尾へ移して`j`を進めるので、途中で配列を縮小しない。`n`は7以上
+    fn at_offset(
+        n: usize,
+        offset: usize,
+    ) -> (ArrayDeque<usize>, std::collections::VecDeque<usize>) {
+        let mut a = ArrayDeque::with_capacity(0);
+        a.reserve(15);
+        a.extend(0..n);
+        for _ in 0..offset {
+            let x = a.remove_front().unwrap();
+            a.add_back(x).unwrap();
+        }
+        assert_eq!((a.a.length(), a.j), (16, offset));
+        a.check_invariants();
+        let v = a.iter().copied().collect();
+        (a, v)
+    }
+
+    #[test]
+    fn wrapped_shift() {
+        // 折り返し位置をずらしながら、すべての位置への挿入と削除をVecDequeと比べる
+        let mut wrapped = 0;
+        for offset in 0..16 {
+            for i in 0..=10 {
+                let (mut a, mut v) = at_offset(10, offset);
+                if i == 0 && !a.as_slices().1.is_empty() {
+                    wrapped += 1;
+                }
+
+                a.add(i, 100).unwrap();
+                v.insert(i, 100);
+                a.check_invariants();
+                assert!(a.iter().eq(v.iter()));
+                assert_eq!(a.remove(i), v.remove(i));
+                a.check_invariants();
+                assert_eq!(a.remove(i.min(9)), v.remove(i.min(9)));
+                a.check_invariants();
+                assert!(a.iter().eq(v.iter()));
+            }
+        }
+        // 10個が配列の末尾で折り返すのは`j`が7以上のとき
+        assert_eq!(wrapped, 9);
+    }
+
+    #[test]
+    fn slices() {
+        // 先頭側への追加で配列の末尾に折り返した状態にする
+        let mut a = "def".chars().collect::<ArrayDeque<_>>();
+        for c in "cba".chars() {
+            a.add_front(c).unwrap();
+        }
+        a.check_invariants();
+        let (head, tail) = a.as_slices();
+        assert!(!tail.is_empty());
+        assert_eq!(head.iter().chain(tail).collect::<String>(), "abcdef");
+
+        let (head, tail) = a.as_mut_slices();
+        head[0] = 'x';
+        tail[tail.len() - 1] = 'y';
+        assert_eq!(a.make_contiguous().iter().collect::<String>(), "xbcdey");
+        a.check_invariants();
+        assert!(a.as_slices().1.is_empty());
+
+        a.make_contiguous().sort_by(|x, y| y.cmp(x));
+        a.check_invariants();
+        assert_eq!(a.iter().collect::<String>(), "yxedcb");
+        a.add_front('z').unwrap();
+        a.check_invariants();
+        assert_eq!(a.iter().collect::<String>(), "zyxedcb");
+    }
+
+    #[test]
+    fn rotate() {
+        // 折り返し位置と回転数をずらしながらVecDequeと比べる
+        let mut wrapped = 0;
+        for offset in 0..16 {
+            for r in 0..=14 {
+                // 空きが少なく、移す区間と移し先が重なる状態にする
+                let (mut a, mut v) = at_offset(14, offset);
+                assert_eq!(a.capacity(), 15);
+                if r == 0 && !a.as_slices().1.is_empty() {
+                    wrapped += 1;
+                }
+
+                a.rotate_left(r);
+                v.rotate_left(r);
+                a.check_invariants();
+                assert!(a.iter().eq(v.iter()));
+                a.rotate_right(r);
+                v.rotate_right(r);
+                a.check_invariants();
+                assert!(a.iter().eq(v.iter()));
+                a.rotate_right(r);
+                v.rotate_right(r);
+                assert_eq!(a.make_contiguous(), v.make_contiguous());
+                a.check_invariants();
+            }
+        }
+        // 14個が配列の末尾で折り返すのは`j`が3以上のとき
+        assert_eq!(wrapped, 13);
+    }
+
+    #[test]
+    fn iter() {
+        // 途中への追加と削除で要素をずらした後も先頭から順に返す
+        let mut a = "abcdefgh".chars().collect::<ArrayDeque<_>>();
+        a.remove(2);
+        a.add(4, 'x').unwrap();
+        a.add(3, 'y').unwrap();
+        a.add(3, 'z').unwrap();
+        a.check_invariants();
+        assert_eq!(a.iter().collect::<String>(), "abdzyexfgh");
+
+        // 先頭側への追加で配列の末尾に折り返した状態にする
+        let mut a = "cde".chars().collect::<ArrayDeque<_>>();
+        a.add_front('b').unwrap();
+        a.add_front('a').unwrap();
+        a.extend("fg".chars());
+        a.check_invariants();
+        assert_eq!(a.iter().collect::<String>(), "abcdefg");
+        assert_eq!(a.iter().rev().collect::<String>(), "gfedcba");
+        assert_eq!(a.iter().len(), 7);
+
+        for x in &mut a {
+            *x = x.to_ascii_uppercase();
+        }
+        assert_eq!(
+            a.iter_mut().rev().map(|x| *x).collect::<String>(),
+            "GFEDCBA"
+        );
+
+        let mut it = a.into_iter();
+        assert_eq!(it.next(), Some('A'));
+        assert_eq!(it.next_back(), Some('G'));
+        assert_eq!(it.len(), 5);
+        assert_eq!(it.collect::<String>(), "BCDEF");
+    }
+
+    #[test]
+    fn index() {
+        let mut a = "bcd".chars().collect::<ArrayDeque<_>>();
+        a.add_front('a').unwrap();
+        assert_eq!(a[0], 'a');
+        assert_eq!(a[3], 'd');
+        a[1] = 'x';
+        if let Some(x) = a.get_mut(2) {
+            *x = 'y';
+        }
+        assert_eq!(a.get_mut(4), None);
+        assert_eq!(a.iter().collect::<String>(), "axyd");
+    }
+
+    #[test]
+    fn traits() {
+        use std::hash::{BuildHasher, RandomState};
+
+        // 先頭の位置が違っても、同じ列なら等しくハッシュも一致する
+        let mut a = ArrayDeque::with_capacity(8);
+        for x in [3, 4, 5] {
+            a.add_back(x).unwrap();
+        }
+        for x in [2, 1] {
+            a.add_front(x).unwrap();
+        }
+        let b = (1..=5).collect::<ArrayDeque<_>>();
+        assert_ne!(a.j, b.j);
+        assert_eq!(a, b);
+        let s = RandomState::new();
+        assert_eq!(s.hash_one(&a), s.hash_one(&b));
+        assert_eq!(s.hash_one(&a), s.hash_one(vec![1, 2, 3, 4, 5]));
+
+        let c = a.clone();
+        assert_eq!(c, a);
+        assert_eq!(format!("{c:?}"), "[1, 2, 3, 4, 5]");
+        assert!(c < (2..3).collect());
+        assert!(c > (1..5).collect());
+        assert_eq!(ArrayDeque::<i32>::default(), ArrayDeque::with_capacity(16));
+    }
+
+    #[test]
+    fn conformance() {
+        use chapter1::testkit;
+
+        for seed in 0..4 {
+            testkit::check_list(ArrayDeque::with_capacity(0), seed, |a| a.check_invariants());
+            testkit::check_deque(ArrayDeque::with_capacity(0), seed, |a| a.check_invariants());
+            testkit::check_stack(ArrayDeque::with_capacity(0), seed, |a| a.check_invariants());
+        }
+    }
+
+    #[test]
+    fn rotate_random() {
+        use std::collections::VecDeque;
+
+        // testkitの操作列には回転と並べ直しがないので、追加や削除と混ぜてVecDequeと比べる
+        for seed in 0..4 {
+            let mut rng = fastrand::Rng::with_seed(seed);
+            let mut a = ArrayDeque::with_capacity(0);
+            let mut v = VecDeque::new();
+            for step in 0..2000 {
+                let r = rng.usize(0..=v.len());
+                match rng.u32(0..6) {
+                    0 => {
+                        a.add_front(step).unwrap();
+                        v.push_front(step);
+                    }
+                    1 => {
+                        a.add_back(step).unwrap();
+                        v.push_back(step);
+                    }
+                    2 => assert_eq!(a.remove_front(), v.pop_front()),
+                    3 => {
+                        a.rotate_left(r);
+                        v.rotate_left(r);
+                    }
+                    4 => {
+                        a.rotate_right(r);
+                        v.rotate_right(r);
+                    }
+                    _ => assert_eq!(a.make_contiguous(), v.make_contiguous()),
+                }
+                a.check_invariants();
+                assert!(a.iter().eq(v.iter()), "seed {seed}, step {step}");
+            }
+        }
+    }
 }
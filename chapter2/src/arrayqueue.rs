@@ -1,56 +1,476 @@
-use chapter1::Queue;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use chapter1::{Capacity, Collection, ListError, Queue};
 
 use crate::Array;
+pub use crate::arraydeque::{IntoIter, Iter, IterMut};
+use crate::arraydeque::{drop_ring, reallocate_ring, wrap_index};
+
+/// 容量を固定した`ArrayQueue`が満杯のときの`add`の振る舞い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Overflow {
+    /// 追加しようとした要素を`ListError::CapacityExhausted`で返す
+    Reject,
+    /// 最も古い要素を取り除いてから追加する
+    Overwrite,
+}
 
+/// `a`を環状配列として使い、`j`から始まる`n`個が初期化済みの要素
+///
+/// 配列長は常に2の冪にして、位置の折り返しを剰余ではなくマスクで計算する。
+/// `bound`が`Some((容量, 満杯時の振る舞い))`のときは容量固定で、容量に見合う配列長を超えて伸長しない
 pub struct ArrayQueue<T> {
     a: Array<T>,
     j: usize,
     n: usize,
+    pub(crate) bound: Option<(usize, Overflow)>,
 }
 
 impl<T> ArrayQueue<T> {
+    /// 配列長は`len`以上の2の冪に切り上げる
     pub fn with_capacity(len: usize) -> Self {
         Self {
-            a: Array::with_capacity(len),
+            a: Array::with_capacity(len.next_power_of_two()),
             j: 0,
             n: 0,
+            bound: None,
         }
     }
 
-    pub fn size(&self) -> usize {
-        self.n
+    /// 最大`capacity`個の要素を保持する容量固定のキューを作る
+    ///
+    /// 満杯のときの`add`は`overflow`に従う。要素数によらず配列の伸長も縮小もしない
+    pub fn bounded(capacity: usize, overflow: Overflow) -> Self {
+        Self {
+            a: Array::with_capacity(capacity.next_power_of_two()),
+            j: 0,
+            n: 0,
+            bound: Some((capacity, overflow)),
+        }
+    }
+
+    /// `bounded`と同じだが、配列は`len`個分だけ先に確保し、容量まで必要になったときに伸ばす
+    ///
+    /// 読み込んだ入力が申告する容量をそのまま確保しないようにする
+    #[cfg(any(test, feature = "serde"))]
+    pub(crate) fn bounded_lazily(capacity: usize, overflow: Overflow, len: usize) -> Self {
+        Self {
+            a: Array::with_capacity(len.min(capacity).next_power_of_two()),
+            j: 0,
+            n: 0,
+            bound: Some((capacity, overflow)),
+        }
+    }
+
+    /// 容量固定なら`true`を返す
+    pub fn is_bounded(&self) -> bool {
+        self.bound.is_some()
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        unsafe { Iter::new(&self.a, self.j, self.n) }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        unsafe { IterMut::new(&mut self.a, self.j, self.n) }
     }
 
     fn resize(&mut self) {
-        let mut new_a = Array::with_capacity((self.n * 2).max(1));
-        let a_size = self.a.length();
-        for k in 0..self.n {
-            core::mem::swap(&mut self.a[(self.j + k) % a_size], &mut new_a[k]);
+        // `ArrayDeque::resize`と同じく空きを1つ残す
+        self.reallocate((self.n + 1) * 2);
+    }
+
+    /// 長さ`len`以上の2の冪の配列を確保して、要素を先頭から詰めて移す
+    ///
+    /// 配列長が変わらなければ何もしない
+    fn reallocate(&mut self, len: usize) {
+        let len = len.next_power_of_two();
+        if len != self.a.length() {
+            self.a = reallocate_ring(&mut self.a, self.j, self.n, len);
+            self.j = 0;
+        }
+    }
+
+    /// 内部の状態が不変条件を満たすかを確かめ、満たさなければpanicする
+    ///
+    /// `ArrayDeque`と同じ条件に加え、容量固定なら要素数が容量以下で配列長が容量に見合っていること
+    #[cfg(any(test, feature = "debug-invariants"))]
+    pub fn check_invariants(&self) {
+        let len = self.a.length();
+        assert!(len.is_power_of_two(), "array length {len}");
+        assert!(self.j < len, "j = {} >= {len}", self.j);
+        match self.bound {
+            Some((capacity, _)) => {
+                assert!(self.n <= capacity, "n = {} > {capacity}", self.n);
+                assert!(self.n <= len, "n = {} > {len}", self.n);
+                assert!(
+                    len <= capacity.next_power_of_two(),
+                    "array length {len} for capacity {capacity}"
+                );
+            }
+            None => assert!(self.n < len, "n = {} >= {len}", self.n),
         }
-        self.a = new_a;
+        let (head, tail) = crate::arraydeque::ring_ranges(len, self.j, self.n);
+        self.a
+            .check_live(|k| head.contains(&k) || tail.contains(&k));
+    }
+}
+
+impl<T> Drop for ArrayQueue<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Collection for ArrayQueue<T> {
+    fn size(&self) -> usize {
+        self.n
+    }
+
+    fn clear(&mut self) {
+        // dropがpanicしても二重にdropしないよう、先に要素数を0にする
+        let n = core::mem::replace(&mut self.n, 0);
+        unsafe { drop_ring(&mut self.a, self.j, n) }
         self.j = 0;
     }
 }
 
+/// `add`は要素数+1が配列長に達すると伸長するので、保持できるのは配列長-1個まで
+///
+/// 容量固定のキューは指定した容量まで保持でき、`reserve`と`shrink_to_fit`は何もしない
+impl<T> Capacity for ArrayQueue<T> {
+    fn capacity(&self) -> usize {
+        match self.bound {
+            Some((capacity, _)) => capacity,
+            None => self.a.length().saturating_sub(1),
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        if !self.is_bounded() && self.n + additional > self.capacity() {
+            self.reallocate(self.n + additional + 1);
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        if !self.is_bounded() && self.capacity() > self.n {
+            self.reallocate(self.n + 1);
+        }
+    }
+}
+
 impl<T> Queue<T> for ArrayQueue<T> {
-    fn add(&mut self, x: T) -> Option<T> {
-        let a_size = self.a.length();
-        if self.size() + 1 >= a_size {
-            self.resize();
-        };
+    fn add(&mut self, x: T) -> Result<(), ListError<T>> {
+        match self.bound {
+            None => {
+                if self.size() + 1 >= self.a.length() {
+                    self.resize();
+                }
+            }
+            Some((capacity, overflow)) if self.n == capacity => match overflow {
+                Overflow::Reject => return Err(ListError::CapacityExhausted(x)),
+                // 容量0なら追加した要素がそのまま押し出される
+                Overflow::Overwrite if self.n == 0 => return Ok(()),
+                Overflow::Overwrite => drop(self.remove()),
+            },
+            // `bounded_lazily`で作ったキューは容量に達するまで伸ばす
+            Some((capacity, _)) if self.n == self.a.length() => {
+                self.reallocate((2 * self.n).min(capacity));
+            }
+            Some(_) => {}
+        }
 
-        let y = self.a[(self.j + self.n) % a_size].replace(x);
+        let k = wrap_index(self.a.length(), self.j + self.n);
+        self.a.write(k, x);
         self.n += 1;
-        y
+        Ok(())
     }
 
     fn remove(&mut self) -> Option<T> {
-        let x = self.a[self.j].take();
-        self.j = (self.j + 1) % self.a.length();
+        // 空の配列から読み出さないようにする
+        if self.n == 0 {
+            return None;
+        }
+        let x = unsafe { self.a.read(self.j) };
+        self.j = wrap_index(self.a.length(), self.j + 1);
         self.n -= 1;
-        if self.a.length() >= 3 * self.n {
+        if !self.is_bounded() && self.a.length() >= 3 * self.n {
             self.resize();
         }
-        x
+        Some(x)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        if self.n == 0 {
+            None
+        } else {
+            Some(unsafe { self.a.get(self.j) })
+        }
+    }
+}
+
+impl<T> FromIterator<T> for ArrayQueue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::with_capacity(0);
+        a.extend(iter);
+        a
+    }
+}
+
+/// 容量固定で`Overflow::Reject`のキューは満杯になったところで止まり、残りの要素は`iter`から取り出さない
+impl<T> Extend<T> for ArrayQueue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        let room = match self.bound {
+            Some((capacity, Overflow::Reject)) => capacity - self.n,
+            _ => usize::MAX,
+        };
+        for x in iter.take(room) {
+            let added = self.add(x);
+            debug_assert!(added.is_ok());
+        }
+    }
+}
+
+impl<T> IntoIterator for ArrayQueue<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let n = core::mem::replace(&mut self.n, 0);
+        let a = core::mem::replace(&mut self.a, Array::with_capacity(0));
+        let j = core::mem::replace(&mut self.j, 0);
+        unsafe { IntoIter::new(a, j, n) }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ArrayQueue<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut ArrayQueue<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: Clone> Clone for ArrayQueue<T> {
+    fn clone(&self) -> Self {
+        // 容量固定かどうかも引き継ぐ
+        let mut q = match self.bound {
+            Some((capacity, overflow)) => Self::bounded(capacity, overflow),
+            None => Self::with_capacity(self.n),
+        };
+        q.extend(self.iter().cloned());
+        q
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArrayQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// 先頭の位置`j`や容量固定かどうかによらず、要素の列が等しければ等しい
+impl<T: PartialEq> PartialEq for ArrayQueue<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for ArrayQueue<T> {}
+
+impl<T: Hash> Hash for ArrayQueue<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size());
+        for x in self.iter() {
+            x.hash(state);
+        }
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for ArrayQueue<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for ArrayQueue<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T> Default for ArrayQueue<T> {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operation() {
+        let mut q = ArrayQueue::with_capacity(0);
+        assert_eq!(q.peek(), None);
+        for c in "abc".chars() {
+            q.add(c).unwrap();
+            q.check_invariants();
+        }
+
+        // 先に入れた要素から取り出される
+        assert_eq!(q.peek(), Some(&'a'));
+        assert_eq!(q.remove(), Some('a'));
+        q.check_invariants();
+        q.add('d').unwrap();
+        q.check_invariants();
+        for c in "bcd".chars() {
+            assert_eq!(q.peek(), Some(&c));
+            assert_eq!(q.remove(), Some(c));
+            q.check_invariants();
+        }
+        assert_eq!(q.peek(), None);
+    }
+
+    #[test]
+    fn remove_empty() {
+        let mut q = ArrayQueue::with_capacity(0);
+        assert_eq!(q.remove(), None);
+        assert_eq!(q.remove(), None);
+        assert_eq!(q.size(), 0);
+        q.add('a').unwrap();
+        assert_eq!(q.remove(), Some('a'));
+        assert_eq!(q.remove(), None);
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn bounded() {
+        let mut q = ArrayQueue::bounded(3, Overflow::Reject);
+        for c in "abc".chars() {
+            q.add(c).unwrap();
+            q.check_invariants();
+        }
+        assert_eq!(q.add('d'), Err(ListError::CapacityExhausted('d')));
+        q.check_invariants();
+        assert_eq!(q.remove(), Some('a'));
+        q.check_invariants();
+        q.add('d').unwrap();
+        q.check_invariants();
+        assert_eq!(q.iter().collect::<String>(), "bcd");
+        // 要素が減っても縮小しない
+        q.clear();
+        q.check_invariants();
+        q.reserve(10);
+        q.check_invariants();
+        assert_eq!(q.capacity(), 3);
+
+        // `extend`は満杯になったところで止まる
+        let mut it = "abcde".chars();
+        q.add('z').unwrap();
+        q.extend(&mut it);
+        q.check_invariants();
+        assert_eq!(q.iter().collect::<String>(), "zab");
+        assert_eq!(it.next(), Some('c'));
+        q.extend("x".chars());
+        q.check_invariants();
+        assert_eq!(q.size(), 3);
+
+        // 古い要素から押し出される
+        let mut q = ArrayQueue::bounded(3, Overflow::Overwrite);
+        for c in "abcde".chars() {
+            q.add(c).unwrap();
+            q.check_invariants();
+        }
+        assert_eq!(q.size(), 3);
+        assert_eq!(q.capacity(), 3);
+        assert_eq!(q.iter().collect::<String>(), "cde");
+        assert_eq!(q.remove(), Some('c'));
+        q.check_invariants();
+        q.add('f').unwrap();
+        q.check_invariants();
+        assert_eq!(q.into_iter().collect::<String>(), "def");
+
+        let mut q = ArrayQueue::bounded(0, Overflow::Overwrite);
+        q.add('a').unwrap();
+        q.check_invariants();
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn bounded_lazily() {
+        // 先に確保した分を超えると、容量に見合う配列長まで伸ばす
+        let mut q = ArrayQueue::bounded_lazily(1 << 40, Overflow::Reject, 3);
+        assert_eq!(q.a.length(), 4);
+        q.extend(0..100);
+        q.check_invariants();
+        assert_eq!(q.a.length(), 128);
+        assert!(q.iter().copied().eq(0..100));
+
+        // 容量に達したら満杯として振る舞う
+        let mut q = ArrayQueue::bounded_lazily(5, Overflow::Overwrite, 1);
+        for x in 0..10 {
+            q.add(x).unwrap();
+            q.check_invariants();
+        }
+        assert_eq!(q.a.length(), 8);
+        assert!(q.iter().copied().eq(5..10));
+    }
+
+    #[test]
+    fn iter() {
+        let mut q = "abc".chars().collect::<ArrayQueue<_>>();
+        q.remove();
+        q.extend("de".chars());
+        q.check_invariants();
+        // 取り出される順に並ぶ
+        assert_eq!(q.iter().collect::<String>(), "bcde");
+        assert_eq!(q.iter().rev().collect::<String>(), "edcb");
+
+        for x in q.iter_mut() {
+            *x = x.to_ascii_uppercase();
+        }
+        assert_eq!(q.into_iter().collect::<String>(), "BCDE");
+    }
+
+    #[test]
+    fn traits() {
+        // 複製しても容量固定のまま
+        let mut q = ArrayQueue::bounded(3, Overflow::Overwrite);
+        q.extend(0..5);
+        let mut r = q.clone();
+        assert!(r.is_bounded());
+        assert_eq!(r, q);
+        r.add(5).unwrap();
+        assert_eq!(format!("{r:?}"), "[3, 4, 5]");
+        assert_ne!(r, q);
+
+        // 容量固定かどうかは比較しない
+        assert_eq!(q, (2..5).collect());
+        assert!(ArrayQueue::<i32>::default().is_empty());
+    }
+
+    #[test]
+    fn conformance() {
+        use chapter1::testkit;
+
+        for seed in 0..4 {
+            testkit::check_fifo(ArrayQueue::with_capacity(0), seed, |a| a.check_invariants());
+        }
     }
 }
@@ -1,7 +1,13 @@
-use chapter1::{List, Queue};
+use chapter1::{Capacity, Collection, List, ListError, Stack};
 
-use super::Array;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::{Index, IndexMut};
 
+use super::{Array, index_out_of_bounds};
+
+/// `a`の`0..n`が初期化済みの要素
 pub struct ArrayStack<T> {
     pub(crate) a: Array<T>,
     pub(crate) n: usize,
@@ -13,136 +19,468 @@ impl<T> ArrayStack<T> {
         Self { a, n: 0 }
     }
 
-    pub fn add_all<I>(&mut self, i: usize, it: I)
+    /// `i`番目に`it`の要素をまとめて挿入する
+    ///
+    /// `i`が範囲外の場合は`it`をそのまま返す
+    pub fn add_all<I>(&mut self, i: usize, it: I) -> Result<(), ListError<I>>
     where
         I: Iterator<Item = T> + ExactSizeIterator,
     {
+        if i > self.n {
+            return Err(ListError::OutOfBounds {
+                index: i,
+                size: self.n,
+                value: it,
+            });
+        }
+
         let added_size = it.len();
         // 要素の伸長
         if self.size() + added_size > self.a.length() {
-            let mut new_a = Array::with_capacity(2 * self.size().max(self.size() + added_size));
-            for k in 0..self.n {
-                core::mem::swap(&mut self.a[k], &mut new_a[k]);
-            }
-            self.a = new_a;
+            self.reallocate(2 * self.size().max(self.size() + added_size));
         }
 
-        for k in (i..self.n).rev() {
-            self.a.swap(k, k + added_size);
+        self.a.copy_within(i..self.n, i + added_size);
+        // 書き込み中に`it`がpanicしても未初期化の要素をdropしないよう、後ろの要素は一旦数えない
+        let tail = self.n - i;
+        self.n = i;
+        for x in it.take(added_size) {
+            self.a.write(self.n, x);
+            self.n += 1;
         }
-        for (k, v) in it.enumerate() {
-            self.a[i + k] = Some(v);
+        // `it`が申告より少ない要素しか返さなかった場合は後ろの要素を詰める
+        if self.n < i + added_size {
+            let tail_start = i + added_size;
+            self.a.copy_within(tail_start..tail_start + tail, self.n);
         }
-        self.n += added_size;
+        self.n += tail;
+        Ok(())
     }
 
-    pub(crate) fn get_mut(&mut self, i: usize) -> Option<&mut Option<T>> {
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.as_slice().iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            inner: self.as_mut_slice().iter_mut(),
+        }
+    }
+
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
         if i < self.n {
-            Some(&mut self.a[i])
+            Some(unsafe { self.a.get_mut(i) })
         } else {
             None
         }
     }
 
+    pub(crate) fn as_slice(&self) -> &[T] {
+        unsafe { self.a.slice(0..self.n) }
+    }
+
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { self.a.slice_mut(0..self.n) }
+    }
+
     pub(crate) fn resize(&mut self) {
-        let mut new_a = Array::with_capacity((2 * self.n).max(1));
-        for i in 0..self.n {
-            core::mem::swap(&mut self.a[i], &mut new_a[i]);
-        }
+        self.reallocate((2 * self.n).max(1));
+    }
+
+    /// 長さ`len`の配列を確保して要素を移す
+    pub(crate) fn reallocate(&mut self, len: usize) {
+        let mut new_a = Array::with_capacity(len);
+        self.a.copy_to(0..self.n, &mut new_a, 0);
         self.a = new_a;
     }
+
+    /// 内部の状態が不変条件を満たすかを確かめ、満たさなければpanicする
+    ///
+    /// `n`が配列長以下で、初期化済みの位置がちょうど`0..n`であること
+    #[cfg(any(test, feature = "debug-invariants"))]
+    pub fn check_invariants(&self) {
+        assert!(
+            self.n <= self.a.length(),
+            "n = {} > {}",
+            self.n,
+            self.a.length()
+        );
+        self.a.check_live(|k| k < self.n);
+    }
 }
 
-impl<T> List<T> for ArrayStack<T> {
+impl<T> Drop for ArrayStack<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Collection for ArrayStack<T> {
     fn size(&self) -> usize {
         self.n
     }
 
+    fn clear(&mut self) {
+        // dropがpanicしても二重にdropしないよう、先に要素数を0にする
+        let n = core::mem::replace(&mut self.n, 0);
+        unsafe { self.a.drop_range(0..n) }
+    }
+}
+
+impl<T> Capacity for ArrayStack<T> {
+    fn capacity(&self) -> usize {
+        self.a.length()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        if self.n + additional > self.a.length() {
+            // 1つずつ`reserve`されても再確保の回数が対数に収まるよう、少なくとも2倍に伸ばす
+            self.reallocate((2 * self.a.length()).max(self.n + additional));
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        if self.a.length() > self.n {
+            self.reallocate(self.n);
+        }
+    }
+}
+
+impl<T> List<T> for ArrayStack<T> {
     fn get(&self, i: usize) -> Option<&T> {
-        if i < self.n { self.a[i].as_ref() } else { None }
+        if i < self.n {
+            Some(unsafe { self.a.get(i) })
+        } else {
+            None
+        }
     }
 
-    fn set(&mut self, i: usize, x: T) -> Option<T> {
+    fn set(&mut self, i: usize, x: T) -> Result<T, ListError<T>> {
         if i < self.n {
-            self.a[i].replace(x)
+            Ok(unsafe { self.a.replace(i, x) })
         } else {
-            Some(x)
+            Err(ListError::OutOfBounds {
+                index: i,
+                size: self.n,
+                value: x,
+            })
         }
     }
 
-    fn add(&mut self, i: usize, x: T) -> Option<T> {
+    fn add(&mut self, i: usize, x: T) -> Result<(), ListError<T>> {
+        if i > self.n {
+            return Err(ListError::OutOfBounds {
+                index: i,
+                size: self.n,
+                value: x,
+            });
+        }
+
         if self.n + 1 > self.a.length() {
             self.resize();
         }
 
-        if i <= self.n {
-            // i..nを右に1つずらす
-            for k in (i..self.n).rev() {
-                self.a.swap(k, k + 1);
-            }
-            let y = self.a[i].replace(x);
-            self.n += 1;
-            y
-        } else {
-            Some(x)
-        }
+        // i..nを右に1つずらす
+        self.a.copy_within(i..self.n, i + 1);
+        self.a.write(i, x);
+        self.n += 1;
+        Ok(())
     }
 
     fn remove(&mut self, i: usize) -> Option<T> {
         if i < self.n {
-            let x = self.a[i].take();
-            for k in i..(self.n - 1) {
-                self.a.swap(k, k + 1);
-            }
+            let x = unsafe { self.a.read(i) };
+            // i+1..nを左に1つずらす
+            self.a.copy_within(i + 1..self.n, i);
             self.n -= 1;
             if self.a.length() >= 3 * self.n {
                 self.resize();
             }
-            x
+            Some(x)
         } else {
             None
         }
     }
 }
 
-impl<T> Queue<T> for ArrayStack<T> {
-    fn add(&mut self, x: T) -> Option<T> {
+impl<T> Stack<T> for ArrayStack<T> {
+    fn push(&mut self, x: T) -> Result<(), ListError<T>> {
         List::add(self, self.size(), x)
     }
 
-    fn remove(&mut self) -> Option<T> {
-        List::remove(self, self.size() - 1)
+    fn pop(&mut self) -> Option<T> {
+        List::remove(self, self.size().checked_sub(1)?)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.get(self.size().checked_sub(1)?)
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut T> {
+        let i = self.size().checked_sub(1)?;
+        self.get_mut(i)
+    }
+}
+
+impl<T> Index<usize> for ArrayStack<T> {
+    type Output = T;
+
+    #[track_caller]
+    fn index(&self, i: usize) -> &Self::Output {
+        match self.get(i) {
+            Some(x) => x,
+            None => index_out_of_bounds(i, self.size()),
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for ArrayStack<T> {
+    #[track_caller]
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
+        let size = self.size();
+        match self.get_mut(i) {
+            Some(x) => x,
+            None => index_out_of_bounds(i, size),
+        }
+    }
+}
+
+impl<T> FromIterator<T> for ArrayStack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::with_capacity(0);
+        a.extend(iter);
+        a
+    }
+}
+
+impl<T> Extend<T> for ArrayStack<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for x in iter {
+            let added = self.push(x);
+            debug_assert!(added.is_ok());
+        }
+    }
+}
+
+impl<T> IntoIterator for ArrayStack<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let back = core::mem::replace(&mut self.n, 0);
+        IntoIter {
+            a: core::mem::replace(&mut self.a, Array::with_capacity(0)),
+            front: 0,
+            back,
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ArrayStack<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut ArrayStack<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: Clone> Clone for ArrayStack<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArrayStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// 配列長によらず、要素の列が等しければ等しい
+impl<T: PartialEq> PartialEq for ArrayStack<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq> Eq for ArrayStack<T> {}
+
+impl<T: Hash> Hash for ArrayStack<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state);
+    }
+}
+
+/// 先頭から辞書式に比べる
+impl<T: PartialOrd> PartialOrd for ArrayStack<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for ArrayStack<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T> Default for ArrayStack<T> {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+/// 要素への参照を先頭から順に返すイテレータ
+pub struct Iter<'a, T> {
+    inner: core::slice::Iter<'a, T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+/// 要素への可変参照を先頭から順に返すイテレータ
+pub struct IterMut<'a, T> {
+    inner: core::slice::IterMut<'a, T>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+/// 要素を先頭から順に取り出すイテレータ
+///
+/// `a`の`front..back`がまだ取り出していない要素
+pub struct IntoIter<T> {
+    a: Array<T>,
+    front: usize,
+    back: usize,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            self.front += 1;
+            Some(unsafe { self.a.read(self.front - 1) })
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            self.back -= 1;
+            Some(unsafe { self.a.read(self.back) })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        let range = self.front..self.back;
+        self.front = self.back;
+        unsafe { self.a.drop_range(range) }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use chapter1::List;
+    use chapter1::{Capacity, Collection, List, ListError, Stack};
 
     #[test]
     fn operation() {
         // Initialize
         let mut a = super::ArrayStack::with_capacity(6);
-        a.add(0, 'b');
-        a.add(1, 'r');
-        a.add(2, 'e');
-        a.add(3, 'd');
+        a.add(0, 'b').unwrap();
+        a.check_invariants();
+        a.add(1, 'r').unwrap();
+        a.check_invariants();
+        a.add(2, 'e').unwrap();
+        a.check_invariants();
+        a.add(3, 'd').unwrap();
+        a.check_invariants();
 
         // Add
-        a.add(2, 'e');
-        a.add(5, 'r');
-        a.add(5, 'e');
+        a.add(2, 'e').unwrap();
+        a.check_invariants();
+        a.add(5, 'r').unwrap();
+        a.check_invariants();
+        a.add(5, 'e').unwrap();
+        a.check_invariants();
 
         // remove
         let x = a.remove(4);
         assert_eq!(x, Some('d'));
+        a.check_invariants();
         let x = a.remove(4);
         assert_eq!(x, Some('e'));
+        a.check_invariants();
         let x = a.remove(4);
         assert_eq!(x, Some('r'));
+        a.check_invariants();
 
         // set
-        a.set(2, 'i');
+        a.set(2, 'i').unwrap();
+        a.check_invariants();
 
         // check
         assert_eq!(a.get(0), Some(&'b'));
@@ -154,23 +492,31 @@ mod tests {
     #[test]
     fn operation_all() {
         let mut a = super::ArrayStack::with_capacity(0);
-        a.add_all(0, ['b', 'r', 'e', 'd'].into_iter());
+        a.add_all(0, ['b', 'r', 'e', 'd'].into_iter()).unwrap();
+        a.check_invariants();
 
         // Add
-        a.add(2, 'e');
-        a.add(5, 'r');
-        a.add(5, 'e');
+        a.add(2, 'e').unwrap();
+        a.check_invariants();
+        a.add(5, 'r').unwrap();
+        a.check_invariants();
+        a.add(5, 'e').unwrap();
+        a.check_invariants();
 
         // remove
         let x = a.remove(4);
         assert_eq!(x, Some('d'));
+        a.check_invariants();
         let x = a.remove(4);
         assert_eq!(x, Some('e'));
+        a.check_invariants();
         let x = a.remove(4);
         assert_eq!(x, Some('r'));
+        a.check_invariants();
 
         // set
-        a.set(2, 'i');
+        a.set(2, 'i').unwrap();
+        a.check_invariants();
 
         // check
         assert_eq!(a.get(0), Some(&'b'));
@@ -178,4 +524,148 @@ mod tests {
         assert_eq!(a.get(2), Some(&'i'));
         assert_eq!(a.get(3), Some(&'e'));
     }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut a = super::ArrayStack::with_capacity(0);
+        a.add(0, 'a').unwrap();
+
+        // 範囲外の要素はそのまま返される
+        assert_eq!(
+            a.add(2, 'b'),
+            Err(ListError::OutOfBounds {
+                index: 2,
+                size: 1,
+                value: 'b'
+            })
+        );
+        assert_eq!(a.set(1, 'c').map_err(ListError::into_inner), Err('c'));
+        assert_eq!(a.set(0, 'd'), Ok('a'));
+        assert!(a.add_all(3, ['e'].into_iter()).is_err());
+        assert_eq!(a.size(), 1);
+        assert_eq!(a.get(0), Some(&'d'));
+    }
+
+    #[test]
+    fn stack() {
+        let mut a = super::ArrayStack::with_capacity(0);
+        assert_eq!(a.pop(), None);
+        a.check_invariants();
+        assert_eq!(a.peek(), None);
+        assert_eq!(a.peek_mut(), None);
+
+        for c in "abc".chars() {
+            a.push(c).unwrap();
+            a.check_invariants();
+        }
+        assert_eq!(a.peek(), Some(&'c'));
+        if let Some(x) = a.peek_mut() {
+            *x = 'x';
+        }
+        assert_eq!(a.pop(), Some('x'));
+        a.check_invariants();
+        assert_eq!(a.pop(), Some('b'));
+        a.check_invariants();
+        assert_eq!(a.pop(), Some('a'));
+        a.check_invariants();
+        assert_eq!(a.pop(), None);
+        a.check_invariants();
+        assert_eq!(a.size(), 0);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut a = super::ArrayStack::with_capacity(0);
+        assert!(a.is_empty());
+        a.reserve(10);
+        assert!(a.capacity() >= 10);
+        for c in "abcdefghij".chars() {
+            a.push(c).unwrap();
+        }
+        assert_eq!(a.size(), 10);
+        assert!(!a.is_empty());
+
+        a.pop();
+        a.shrink_to_fit();
+        assert_eq!(a.capacity(), 9);
+        assert_eq!(a.peek(), Some(&'i'));
+
+        a.clear();
+        assert!(a.is_empty());
+        assert_eq!(a.get(0), None);
+        a.push('z').unwrap();
+        assert_eq!(a.get(0), Some(&'z'));
+
+        // 1要素ずつ`extend`しても配列長は倍々に伸びる
+        let mut a = super::ArrayStack::with_capacity(0);
+        let mut reallocations = 0;
+        for x in 0..1000 {
+            let capacity = a.capacity();
+            a.extend([x]);
+            if a.capacity() != capacity {
+                reallocations += 1;
+            }
+        }
+        assert!(reallocations <= 11, "{reallocations}");
+    }
+
+    #[test]
+    fn iter() {
+        let mut a = "bred".chars().collect::<super::ArrayStack<_>>();
+        a.extend("ed".chars());
+        a.check_invariants();
+        assert_eq!(a.iter().collect::<String>(), "breded");
+        assert_eq!(a.iter().rev().collect::<String>(), "dederb");
+        assert_eq!(a.iter().len(), 6);
+
+        for x in a.iter_mut() {
+            *x = x.to_ascii_uppercase();
+        }
+        for x in &mut a {
+            if *x == 'E' {
+                *x = 'i';
+            }
+        }
+        assert_eq!((&a).into_iter().collect::<String>(), "BRiDiD");
+
+        let mut it = a.into_iter();
+        assert_eq!(it.next(), Some('B'));
+        assert_eq!(it.next_back(), Some('D'));
+        assert_eq!(it.len(), 4);
+        assert_eq!(it.collect::<String>(), "RiDi");
+    }
+
+    #[test]
+    fn index() {
+        let mut a = "abcd".chars().collect::<super::ArrayStack<_>>();
+        assert_eq!(a[0], 'a');
+        assert_eq!(a[3], 'd');
+        a[1] = 'x';
+        if let Some(x) = a.get_mut(2) {
+            *x = 'y';
+        }
+        assert_eq!(a.get_mut(4), None);
+        assert_eq!(a.iter().collect::<String>(), "axyd");
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds: the len is 2 but the index is 2")]
+    fn index_out_of_bounds() {
+        let a = "ab".chars().collect::<super::ArrayStack<_>>();
+        let _ = a[2];
+    }
+
+    #[test]
+    fn conformance() {
+        use chapter1::testkit;
+
+        for seed in 0..4 {
+            testkit::check_list(super::ArrayStack::with_capacity(0), seed, |a| {
+                a.check_invariants()
+            });
+            testkit::check_stack(super::ArrayStack::with_capacity(0), seed, |a| {
+                a.check_invariants()
+            });
+        }
+    }
 }
@@ -0,0 +1,270 @@
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use chapter1::{Capacity, Collection, List, ListError, PriorityQueue};
+
+use crate::{arraystack::ArrayStack, sorted};
+
+/// 完全二分木を`ArrayStack`に幅優先順で並べたヒープ
+///
+/// 各ノードは親以上の値を持つので、根(インデックス0)が最小になる
+///
+/// 同じ要素でも追加の順序で並びが変わるので、比較やハッシュは要素を昇順に並べた列で行う
+#[derive(Clone)]
+pub struct BinaryHeap<T> {
+    a: ArrayStack<T>,
+}
+
+impl<T: Ord> BinaryHeap<T> {
+    pub fn with_capacity(len: usize) -> Self {
+        Self {
+            a: ArrayStack::with_capacity(len),
+        }
+    }
+
+    fn left(i: usize) -> usize {
+        2 * i + 1
+    }
+
+    fn right(i: usize) -> usize {
+        2 * i + 2
+    }
+
+    fn parent(i: usize) -> usize {
+        (i - 1) / 2
+    }
+
+    /// `i`番目の要素が`j`番目の要素より小さいか
+    fn less(&self, i: usize, j: usize) -> bool {
+        self.a.get(i) < self.a.get(j)
+    }
+
+    /// `i`番目の要素を親より小さい間だけ上へ移動する
+    fn bubble_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let p = Self::parent(i);
+            if !self.less(i, p) {
+                break;
+            }
+            self.a.a.swap(i, p);
+            i = p;
+        }
+    }
+
+    /// `i`番目の要素を子より大きい間だけ下へ移動する
+    fn trickle_down(&mut self, mut i: usize) {
+        let n = self.a.size();
+        loop {
+            let (l, r) = (Self::left(i), Self::right(i));
+            let mut j = i;
+            if l < n && self.less(l, j) {
+                j = l;
+            }
+            if r < n && self.less(r, j) {
+                j = r;
+            }
+            if j == i {
+                break;
+            }
+            self.a.a.swap(i, j);
+            i = j;
+        }
+    }
+
+    /// 内部の状態が不変条件を満たすかを確かめ、満たさなければpanicする
+    ///
+    /// どのノードも親以上であること
+    #[cfg(any(test, feature = "debug-invariants"))]
+    pub fn check_invariants(&self) {
+        self.a.check_invariants();
+        if let Some(i) = (1..self.a.size()).find(|&i| self.less(i, Self::parent(i))) {
+            panic!("element {i} is less than its parent");
+        }
+    }
+}
+
+impl<T> Collection for BinaryHeap<T> {
+    fn size(&self) -> usize {
+        self.a.size()
+    }
+
+    fn clear(&mut self) {
+        self.a.clear();
+    }
+}
+
+impl<T> Capacity for BinaryHeap<T> {
+    fn capacity(&self) -> usize {
+        self.a.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.a.reserve(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.a.shrink_to_fit();
+    }
+}
+
+impl<T: Ord> PriorityQueue<T> for BinaryHeap<T> {
+    fn add(&mut self, x: T) -> Result<(), ListError<T>> {
+        let n = self.a.size();
+        self.a.add(n, x)?;
+        self.bubble_up(n);
+        Ok(())
+    }
+
+    fn remove_min(&mut self) -> Option<T> {
+        let last = self.a.size().checked_sub(1)?;
+        // 根と末尾を入れ替えて末尾を取り除く
+        self.a.a.swap(0, last);
+        let x = self.a.remove(last);
+        self.trickle_down(0);
+        x
+    }
+
+    fn peek_min(&self) -> Option<&T> {
+        self.a.get(0)
+    }
+}
+
+impl<T: Ord> FromIterator<T> for BinaryHeap<T> {
+    /// 要素を並べてから下から順に`trickle_down`することで、O(n)でヒープを作る
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut heap = Self::with_capacity(iter.size_hint().0);
+        for x in iter {
+            let n = heap.a.size();
+            let added = heap.a.add(n, x);
+            debug_assert!(added.is_ok());
+        }
+        for i in (0..heap.a.size() / 2).rev() {
+            heap.trickle_down(i);
+        }
+        heap
+    }
+}
+
+/// 配列に並んでいる順に表示する
+impl<T: fmt::Debug> fmt::Debug for BinaryHeap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.a.iter()).finish()
+    }
+}
+
+/// 配列での並びによらず、同じ要素を同じ数だけ持っていれば等しい
+impl<T: Ord> PartialEq for BinaryHeap<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && sorted(self.a.iter()) == sorted(other.a.iter())
+    }
+}
+
+impl<T: Ord> Eq for BinaryHeap<T> {}
+
+impl<T: Ord + Hash> Hash for BinaryHeap<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size());
+        for x in sorted(self.a.iter()) {
+            x.hash(state);
+        }
+    }
+}
+
+/// 昇順に並べた列を辞書式に比べる
+impl<T: Ord> PartialOrd for BinaryHeap<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for BinaryHeap<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        sorted(self.a.iter()).cmp(&sorted(other.a.iter()))
+    }
+}
+
+impl<T> Default for BinaryHeap<T> {
+    fn default() -> Self {
+        Self {
+            a: ArrayStack::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operation() {
+        // Initialize
+        let mut h = BinaryHeap::with_capacity(0);
+        for x in [4, 9, 8, 17, 26, 50, 16, 19, 69, 32, 93, 55] {
+            h.add(x).unwrap();
+            h.check_invariants();
+        }
+        assert_eq!(h.peek_min(), Some(&4));
+
+        // Add
+        h.add(6).unwrap();
+        h.check_invariants();
+        assert_eq!(h.peek_min(), Some(&4));
+        h.add(1).unwrap();
+        h.check_invariants();
+        assert_eq!(h.peek_min(), Some(&1));
+
+        // Remove
+        for x in [1, 4, 6] {
+            assert_eq!(h.remove_min(), Some(x));
+            h.check_invariants();
+        }
+        assert_eq!(h.peek_min(), Some(&8));
+        assert_eq!(h.size(), 11);
+    }
+
+    #[test]
+    fn from_iter() {
+        let xs = [5, 3, 8, 1, 9, 2, 7, 3, 0, 6];
+        let mut h = xs.into_iter().collect::<BinaryHeap<_>>();
+        assert_eq!(h.size(), xs.len());
+        h.check_invariants();
+
+        let mut sorted = xs.to_vec();
+        sorted.sort();
+        let mut removed = Vec::new();
+        while let Some(x) = h.remove_min() {
+            h.check_invariants();
+            removed.push(x);
+        }
+        assert_eq!(removed, sorted);
+        assert_eq!(h.peek_min(), None);
+    }
+
+    #[test]
+    fn traits() {
+        use std::hash::{BuildHasher, RandomState};
+
+        // 追加の順序で配列の並びが変わっても、同じ要素を同じ数だけ持っていれば等しい
+        let a = [3, 1, 2, 1].into_iter().collect::<BinaryHeap<_>>();
+        let mut b = BinaryHeap::with_capacity(0);
+        for x in [1, 2, 1, 3] {
+            b.add(x).unwrap();
+        }
+        assert_ne!(format!("{a:?}"), format!("{b:?}"));
+        assert_eq!(a, b);
+        let s = RandomState::new();
+        assert_eq!(s.hash_one(&a), s.hash_one(&b));
+
+        // 重複の数も比べる
+        b.remove_min();
+        b.add(2).unwrap();
+        assert_ne!(a, b);
+        // 昇順に並べると[1, 1, 2, 3]と[1, 2, 2, 3]
+        assert!(a < b);
+        assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+        assert_eq!(a.clone(), a);
+        assert_eq!(BinaryHeap::<i32>::default(), BinaryHeap::with_capacity(8));
+    }
+}
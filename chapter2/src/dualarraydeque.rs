@@ -1,23 +1,127 @@
-use chapter1::List;
+use chapter1::{Capacity, Collection, Deque, List, ListError, Queue, Stack};
 
-use crate::{Array, arraystack::ArrayStack};
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::iter::Rev;
+use core::marker::PhantomData;
+use core::ops::{Index, IndexMut};
 
-pub struct DualArrayDeque<T> {
-    front: ArrayStack<T>,
-    back: ArrayStack<T>,
+use crate::{arraystack::ArrayStack, index_out_of_bounds};
+
+/// 2つのスタックを背中合わせにした両端キュー
+///
+/// `front`には先頭側の要素が逆順に、`back`には残りの要素が順に入っている。
+/// スタックの型`S`で確保のしかたが決まり、`ArrayStack`なら`DualArrayDeque`、
+/// `RootishArrayStack`なら`RootishArrayDeque`になる
+pub struct DualDeque<T, S> {
+    front: S,
+    back: S,
+    marker: PhantomData<T>,
+}
+
+/// `ArrayStack`を2つ使う両端キュー
+pub type DualArrayDeque<T> = DualDeque<T, ArrayStack<T>>;
+
+/// `DequeHalf`をこのクレートの外で実装できないようにする
+///
+/// `check_invariants`はフィーチャによって有無が変わるので、外で実装されると
+/// `debug-invariants`を有効にしたときにその実装がコンパイルできなくなる
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
+
+/// `DualDeque`の`front`と`back`に使うスタック
+///
+/// `DualArrayDeque`と`RootishArrayDeque`のためのもので、このクレートの外では実装できない
+pub trait DequeHalf<T>:
+    sealed::Sealed
+    + List<T>
+    + Capacity
+    + Default
+    + FromIterator<T>
+    + IntoIterator<Item = T, IntoIter: DoubleEndedIterator + ExactSizeIterator>
+{
+    type Iter<'a>: DoubleEndedIterator<Item = &'a T> + ExactSizeIterator
+    where
+        Self: 'a,
+        T: 'a;
+    type IterMut<'a>: DoubleEndedIterator<Item = &'a mut T> + ExactSizeIterator
+    where
+        Self: 'a,
+        T: 'a;
+
+    fn iter(&self) -> Self::Iter<'_>;
+    fn iter_mut(&mut self) -> Self::IterMut<'_>;
+    fn get_mut(&mut self, i: usize) -> Option<&mut T>;
+    /// 要素の並びを逆にする
+    fn reverse(&mut self);
+    #[cfg(any(test, feature = "debug-invariants"))]
+    fn check_invariants(&self);
+}
+
+impl<T> sealed::Sealed for ArrayStack<T> {}
+
+impl<T> DequeHalf<T> for ArrayStack<T> {
+    type Iter<'a>
+        = crate::arraystack::Iter<'a, T>
+    where
+        T: 'a;
+    type IterMut<'a>
+        = crate::arraystack::IterMut<'a, T>
+    where
+        T: 'a;
+
+    fn iter(&self) -> Self::Iter<'_> {
+        ArrayStack::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        ArrayStack::iter_mut(self)
+    }
+
+    fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        ArrayStack::get_mut(self, i)
+    }
+
+    fn reverse(&mut self) {
+        self.as_mut_slice().reverse();
+    }
+
+    #[cfg(any(test, feature = "debug-invariants"))]
+    fn check_invariants(&self) {
+        ArrayStack::check_invariants(self);
+    }
 }
 
 impl<T> DualArrayDeque<T> {
     pub fn with_capacity(len: usize) -> Self {
         let n_front = len / 2;
         let n_back = len - n_front;
-        DualArrayDeque {
+        DualDeque {
             front: ArrayStack::with_capacity(n_front),
             back: ArrayStack::with_capacity(n_back),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, S: DequeHalf<T>> DualDeque<T, S> {
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        Iter {
+            front: self.front.iter().rev(),
+            back: self.back.iter(),
         }
     }
 
-    fn get_mut(&mut self, i: usize) -> Option<&mut Option<T>> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, S> {
+        IterMut {
+            front: self.front.iter_mut().rev(),
+            back: self.back.iter_mut(),
+        }
+    }
+
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
         if i < self.size() {
             let front_size = self.front.size();
             if i < front_size {
@@ -34,36 +138,63 @@ impl<T> DualArrayDeque<T> {
         let back_too_big = 3 * self.front.size() < self.back.size();
         let front_too_big = 3 * self.back.size() < self.front.size();
         if back_too_big || front_too_big {
-            let n = self.front.size() + self.back.size();
-            let n_front = n / 2;
-            let mut new_front = Array::with_capacity((2 * n_front).max(1));
-            for i in 0..n_front {
-                if let Some(p_cur) = self.get_mut(i) {
-                    core::mem::swap(&mut new_front[n_front - i - 1], p_cur);
-                }
-            }
+            let n = self.size();
+            // 先頭から順に取り出して詰め直す
+            let mut it = core::mem::take(self).into_iter();
 
-            let n_back = n - n_front;
-            let mut new_back = Array::with_capacity((2 * n_back).max(1));
-            for i in 0..n_back {
-                if let Some(p_cur) = self.get_mut(n_front + i) {
-                    core::mem::swap(&mut new_back[i], p_cur);
-                }
-            }
-
-            self.front.a = new_front;
-            self.front.n = n_front;
-            self.back.a = new_back;
-            self.back.n = n_back;
+            let mut front = it.by_ref().take(n / 2).collect::<S>();
+            front.reverse();
+            self.front = front;
+            self.back = it.collect();
         }
     }
+
+    /// 内部の状態が不変条件を満たすかを確かめ、満たさなければpanicする
+    ///
+    /// `front`と`back`がそれぞれ整合していて、`balance`の条件どおりどちらも他方の3倍を超えないこと
+    #[cfg(any(test, feature = "debug-invariants"))]
+    pub fn check_invariants(&self) {
+        self.front.check_invariants();
+        self.back.check_invariants();
+        let (f, b) = (self.front.size(), self.back.size());
+        // 要素が1つのときはどちらかが空になるので、釣り合いは2つ以上で確かめる
+        assert!(
+            f + b < 2 || (3 * f >= b && 3 * b >= f),
+            "front {f} and back {b} are out of balance"
+        );
+    }
 }
 
-impl<T> List<T> for DualArrayDeque<T> {
+impl<T, S: DequeHalf<T>> Collection for DualDeque<T, S> {
     fn size(&self) -> usize {
         self.front.size() + self.back.size()
     }
 
+    fn clear(&mut self) {
+        self.front.clear();
+        self.back.clear();
+    }
+}
+
+/// 確保量はfrontとbackの合計。`balance`で作り直すと確保量も変わる
+impl<T, S: DequeHalf<T>> Capacity for DualDeque<T, S> {
+    fn capacity(&self) -> usize {
+        self.front.capacity() + self.back.capacity()
+    }
+
+    /// どちらの端に追加しても`additional`個までは再確保しないよう、両方に確保する
+    fn reserve(&mut self, additional: usize) {
+        self.front.reserve(additional);
+        self.back.reserve(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.front.shrink_to_fit();
+        self.back.shrink_to_fit();
+    }
+}
+
+impl<T, S: DequeHalf<T>> List<T> for DualDeque<T, S> {
     fn get(&self, i: usize) -> Option<&T> {
         if i < self.size() {
             let front_size = self.front.size();
@@ -77,7 +208,7 @@ impl<T> List<T> for DualArrayDeque<T> {
         }
     }
 
-    fn set(&mut self, i: usize, x: T) -> Option<T> {
+    fn set(&mut self, i: usize, x: T) -> Result<T, ListError<T>> {
         if i < self.size() {
             let front_size = self.front.size();
             if i < front_size {
@@ -86,13 +217,22 @@ impl<T> List<T> for DualArrayDeque<T> {
                 self.back.set(i - front_size, x)
             }
         } else {
-            Some(x)
+            Err(ListError::OutOfBounds {
+                index: i,
+                size: self.size(),
+                value: x,
+            })
         }
     }
 
-    fn add(&mut self, i: usize, x: T) -> Option<T> {
+    /// 近い方の端から要素をずらすので、O(1 + min(i, n - i))
+    fn add(&mut self, i: usize, x: T) -> Result<(), ListError<T>> {
         if i > self.size() {
-            return Some(x);
+            return Err(ListError::OutOfBounds {
+                index: i,
+                size: self.size(),
+                value: x,
+            });
         }
 
         let y = if i < self.front.size() {
@@ -119,25 +259,306 @@ impl<T> List<T> for DualArrayDeque<T> {
     }
 }
 
+/// 両端への追加・削除はfrontかbackの末尾への操作になるので、`balance`を含めて償却O(1)
+impl<T, S: DequeHalf<T>> Deque<T> for DualDeque<T, S> {
+    fn add_front(&mut self, x: T) -> Result<(), ListError<T>> {
+        List::add(self, 0, x)
+    }
+
+    fn remove_front(&mut self) -> Option<T> {
+        List::remove(self, 0)
+    }
+
+    fn add_back(&mut self, x: T) -> Result<(), ListError<T>> {
+        List::add(self, self.size(), x)
+    }
+
+    fn remove_back(&mut self) -> Option<T> {
+        List::remove(self, self.size().checked_sub(1)?)
+    }
+
+    fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    fn back(&self) -> Option<&T> {
+        self.get(self.size().checked_sub(1)?)
+    }
+
+    fn front_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(0)
+    }
+
+    fn back_mut(&mut self) -> Option<&mut T> {
+        let i = self.size().checked_sub(1)?;
+        self.get_mut(i)
+    }
+}
+
+/// 末尾に追加し、先頭から取り出す(FIFO)
+impl<T, S: DequeHalf<T>> Queue<T> for DualDeque<T, S> {
+    fn add(&mut self, x: T) -> Result<(), ListError<T>> {
+        self.add_back(x)
+    }
+
+    fn remove(&mut self) -> Option<T> {
+        self.remove_front()
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.front()
+    }
+}
+
+impl<T, S: DequeHalf<T>> Stack<T> for DualDeque<T, S> {
+    fn push(&mut self, x: T) -> Result<(), ListError<T>> {
+        self.add_back(x)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.remove_back()
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.back()
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut T> {
+        self.back_mut()
+    }
+}
+
+impl<T, S: DequeHalf<T>> Index<usize> for DualDeque<T, S> {
+    type Output = T;
+
+    #[track_caller]
+    fn index(&self, i: usize) -> &Self::Output {
+        match self.get(i) {
+            Some(x) => x,
+            None => index_out_of_bounds(i, self.size()),
+        }
+    }
+}
+
+impl<T, S: DequeHalf<T>> IndexMut<usize> for DualDeque<T, S> {
+    #[track_caller]
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
+        let size = self.size();
+        match self.get_mut(i) {
+            Some(x) => x,
+            None => index_out_of_bounds(i, size),
+        }
+    }
+}
+
+impl<T, S: DequeHalf<T>> FromIterator<T> for DualDeque<T, S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::default();
+        a.extend(iter);
+        a
+    }
+}
+
+impl<T, S: DequeHalf<T>> Extend<T> for DualDeque<T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            let added = self.add_back(x);
+            debug_assert!(added.is_ok());
+        }
+    }
+}
+
+impl<T, S: DequeHalf<T>> IntoIterator for DualDeque<T, S> {
+    type Item = T;
+    type IntoIter = IntoIter<T, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            front: self.front.into_iter().rev(),
+            back: self.back.into_iter(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, S: DequeHalf<T>> IntoIterator for &'a DualDeque<T, S> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, S: DequeHalf<T>> IntoIterator for &'a mut DualDeque<T, S> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: Clone, S: DequeHalf<T>> Clone for DualDeque<T, S> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug, S: DequeHalf<T>> fmt::Debug for DualDeque<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// `front`と`back`への要素の分け方によらず、要素の列が等しければ等しい
+impl<T: PartialEq, S: DequeHalf<T>> PartialEq for DualDeque<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, S: DequeHalf<T>> Eq for DualDeque<T, S> {}
+
+impl<T: Hash, S: DequeHalf<T>> Hash for DualDeque<T, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size());
+        for x in self.iter() {
+            x.hash(state);
+        }
+    }
+}
+
+impl<T: PartialOrd, S: DequeHalf<T>> PartialOrd for DualDeque<T, S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord, S: DequeHalf<T>> Ord for DualDeque<T, S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T, S: DequeHalf<T>> Default for DualDeque<T, S> {
+    fn default() -> Self {
+        Self {
+            front: S::default(),
+            back: S::default(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// 要素への参照を先頭から順に返すイテレータ
+///
+/// frontは逆順に格納されているので、frontを末尾から辿ってからbackを辿る
+pub struct Iter<'a, T: 'a, S: DequeHalf<T> + 'a = ArrayStack<T>> {
+    front: Rev<S::Iter<'a>>,
+    back: S::Iter<'a>,
+}
+
+impl<'a, T: 'a, S: DequeHalf<T> + 'a> Iterator for Iter<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.front.next().or_else(|| self.back.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: 'a, S: DequeHalf<T> + 'a> DoubleEndedIterator for Iter<'a, T, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<'a, T: 'a, S: DequeHalf<T> + 'a> ExactSizeIterator for Iter<'a, T, S> {}
+
+/// 要素への可変参照を先頭から順に返すイテレータ
+pub struct IterMut<'a, T: 'a, S: DequeHalf<T> + 'a = ArrayStack<T>> {
+    front: Rev<S::IterMut<'a>>,
+    back: S::IterMut<'a>,
+}
+
+impl<'a, T: 'a, S: DequeHalf<T> + 'a> Iterator for IterMut<'a, T, S> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.front.next().or_else(|| self.back.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: 'a, S: DequeHalf<T> + 'a> DoubleEndedIterator for IterMut<'a, T, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<'a, T: 'a, S: DequeHalf<T> + 'a> ExactSizeIterator for IterMut<'a, T, S> {}
+
+/// 要素を先頭から順に取り出すイテレータ
+pub struct IntoIter<T, S: DequeHalf<T> = ArrayStack<T>> {
+    front: Rev<S::IntoIter>,
+    back: S::IntoIter,
+    marker: PhantomData<T>,
+}
+
+impl<T, S: DequeHalf<T>> Iterator for IntoIter<T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.front.next().or_else(|| self.back.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<T, S: DequeHalf<T>> DoubleEndedIterator for IntoIter<T, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<T, S: DequeHalf<T>> ExactSizeIterator for IntoIter<T, S> {}
+
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use super::DualArrayDeque;
+    use chapter1::{Collection, Deque, List, Stack};
 
     #[test]
     fn operation() {
         // Initialize
         let mut a = DualArrayDeque::with_capacity(10);
         for (i, c) in "abcd".chars().enumerate() {
-            a.add(i, c);
+            a.add(i, c).unwrap();
+            a.check_invariants();
         }
 
         // Add
-        a.add(3, 'x');
-        a.add(4, 'y');
+        a.add(3, 'x').unwrap();
+        a.check_invariants();
+        a.add(4, 'y').unwrap();
+        a.check_invariants();
 
         // Remove
         let x = a.remove(0);
         assert_eq!(x, Some('a'));
+        a.check_invariants();
 
         // Check
         for (i, c) in "bcxyd".chars().enumerate() {
@@ -145,4 +566,237 @@ mod tests {
             assert_eq!(x, Some(&c));
         }
     }
+
+    #[test]
+    fn stack() {
+        let mut a = DualArrayDeque::with_capacity(0);
+        assert_eq!(a.pop(), None);
+        a.check_invariants();
+        assert_eq!(a.peek(), None);
+        assert_eq!(a.peek_mut(), None);
+
+        for c in "abc".chars() {
+            a.push(c).unwrap();
+            a.check_invariants();
+        }
+        assert_eq!(a.peek(), Some(&'c'));
+        if let Some(x) = a.peek_mut() {
+            *x = 'x';
+        }
+        assert_eq!(a.pop(), Some('x'));
+        a.check_invariants();
+        assert_eq!(a.pop(), Some('b'));
+        a.check_invariants();
+        assert_eq!(a.pop(), Some('a'));
+        a.check_invariants();
+        assert_eq!(a.pop(), None);
+        a.check_invariants();
+        assert_eq!(a.size(), 0);
+    }
+
+    #[test]
+    fn front_back() {
+        let mut a = DualArrayDeque::with_capacity(0);
+        assert_eq!(a.front(), None);
+        assert_eq!(a.back_mut(), None);
+
+        // 要素がfrontとbackのどちらにあっても両端を返す
+        a.add(0, 'b').unwrap();
+        assert_eq!(a.front(), Some(&'b'));
+        assert_eq!(a.back(), Some(&'b'));
+        a.add(0, 'a').unwrap();
+        a.add(2, 'c').unwrap();
+        assert_eq!(a.front(), Some(&'a'));
+        assert_eq!(a.back(), Some(&'c'));
+
+        if let Some(x) = a.front_mut() {
+            *x = 'x';
+        }
+        if let Some(x) = a.back_mut() {
+            *x = 'z';
+        }
+        assert_eq!(a.get(0), Some(&'x'));
+        assert_eq!(a.get(2), Some(&'z'));
+    }
+
+    #[test]
+    fn queue() {
+        // ListやStackと同名のメソッドがあるのでトレイト名で呼ぶ
+        use chapter1::Queue;
+
+        let mut q = DualArrayDeque::with_capacity(0);
+        assert_eq!(Queue::peek(&q), None);
+        for c in "abc".chars() {
+            Queue::add(&mut q, c).unwrap();
+            q.check_invariants();
+        }
+        // 先に入れた要素から取り出される
+        assert_eq!(Queue::peek(&q), Some(&'a'));
+        assert_eq!(Queue::remove(&mut q), Some('a'));
+        q.check_invariants();
+        Queue::add(&mut q, 'd').unwrap();
+        q.check_invariants();
+        for c in "bcd".chars() {
+            assert_eq!(Queue::peek(&q), Some(&c));
+            assert_eq!(Queue::remove(&mut q), Some(c));
+            q.check_invariants();
+        }
+        assert_eq!(Queue::remove(&mut q), None);
+    }
+
+    /// frontとbackのどちらも、もう一方の3倍を超えていない
+    fn is_balanced<T>(a: &DualArrayDeque<T>) -> bool {
+        let (f, b) = (a.front.size(), a.back.size());
+        3 * f >= b && 3 * b >= f
+    }
+
+    #[test]
+    fn balance_from_front() {
+        // 先頭側だけに追加してもbackへ移される
+        let mut a = DualArrayDeque::with_capacity(0);
+        for x in (0..100).rev() {
+            a.add_front(x).unwrap();
+            assert!(a.size() < 2 || is_balanced(&a));
+        }
+        assert!(a.iter().copied().eq(0..100));
+
+        // 末尾側から取り出し続けるとfrontの要素がbackへ移される
+        for x in (50..100).rev() {
+            assert_eq!(a.remove_back(), Some(x));
+            assert!(a.size() < 2 || is_balanced(&a));
+        }
+        assert!(a.iter().copied().eq(0..50));
+        assert_eq!(a.front(), Some(&0));
+        assert_eq!(a.back(), Some(&49));
+    }
+
+    #[test]
+    fn balance_from_back() {
+        let mut a = DualArrayDeque::with_capacity(0);
+        for x in 0..100 {
+            a.add_back(x).unwrap();
+            assert!(a.size() < 2 || is_balanced(&a));
+        }
+        for x in 0..99 {
+            assert_eq!(a.remove_front(), Some(x));
+            assert!(a.size() < 2 || is_balanced(&a));
+        }
+        assert_eq!(a.front(), Some(&99));
+        assert_eq!(a.back(), Some(&99));
+        assert_eq!(a.remove_front(), Some(99));
+        assert_eq!(a.remove_front(), None);
+        assert_eq!(a.remove_back(), None);
+
+        // 両端から交互に追加・削除する
+        for x in 0..10 {
+            a.add_front(-x).unwrap();
+            a.add_back(x).unwrap();
+        }
+        for _ in 0..5 {
+            a.remove_front();
+            a.remove_back();
+            assert!(is_balanced(&a));
+        }
+        assert!(a.iter().copied().eq((-4..=0).chain(0..5)));
+    }
+
+    #[test]
+    fn iter() {
+        // 途中への追加と削除の後も先頭から順に返す
+        let mut a = "abcd".chars().collect::<DualArrayDeque<_>>();
+        a.add(3, 'x').unwrap();
+        a.check_invariants();
+        a.add(4, 'y').unwrap();
+        a.check_invariants();
+        a.remove(0);
+        a.check_invariants();
+        assert_eq!(a.iter().collect::<String>(), "bcxyd");
+
+        let mut a = "cd".chars().collect::<DualArrayDeque<_>>();
+        // 先頭への追加はfrontに入る
+        a.add(0, 'b').unwrap();
+        a.check_invariants();
+        a.add(0, 'a').unwrap();
+        a.check_invariants();
+        a.extend("ef".chars());
+        a.check_invariants();
+        assert_eq!(a.iter().collect::<String>(), "abcdef");
+        assert_eq!(a.iter().rev().collect::<String>(), "fedcba");
+        assert_eq!(a.iter().len(), 6);
+
+        for x in &mut a {
+            *x = x.to_ascii_uppercase();
+        }
+        assert_eq!(a.iter_mut().rev().map(|x| *x).collect::<String>(), "FEDCBA");
+
+        let mut it = a.into_iter();
+        assert_eq!(it.next(), Some('A'));
+        assert_eq!(it.next_back(), Some('F'));
+        assert_eq!(it.len(), 4);
+        assert_eq!(it.collect::<String>(), "BCDE");
+    }
+
+    #[test]
+    fn index() {
+        let mut a = "cd".chars().collect::<DualArrayDeque<_>>();
+        a.add(0, 'b').unwrap();
+        a.add(0, 'a').unwrap();
+        assert_eq!(a[0], 'a');
+        assert_eq!(a[3], 'd');
+        a[1] = 'x';
+        if let Some(x) = a.get_mut(2) {
+            *x = 'y';
+        }
+        assert_eq!(a.get_mut(4), None);
+        assert_eq!(a.iter().collect::<String>(), "axyd");
+    }
+
+    #[test]
+    fn traits() {
+        // frontとbackへの分け方が違っても、同じ列なら等しい
+        let mut a = DualArrayDeque::with_capacity(0);
+        for c in "cba".chars() {
+            a.add_front(c).unwrap();
+        }
+        let b = "abc".chars().collect::<DualArrayDeque<_>>();
+        assert_ne!(a.front.size(), b.front.size());
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), core::cmp::Ordering::Equal);
+        assert_eq!(format!("{:?}", a.clone()), "['a', 'b', 'c']");
+        assert_eq!(
+            DualArrayDeque::default(),
+            DualArrayDeque::<char>::with_capacity(4)
+        );
+    }
+
+    #[test]
+    fn conformance() {
+        use chapter1::testkit;
+
+        for seed in 0..4 {
+            testkit::check_list(DualArrayDeque::with_capacity(0), seed, |a| {
+                a.check_invariants()
+            });
+            testkit::check_deque(DualArrayDeque::with_capacity(0), seed, |a| {
+                a.check_invariants()
+            });
+            testkit::check_fifo(DualArrayDeque::with_capacity(0), seed, |a| {
+                a.check_invariants()
+            });
+            testkit::check_stack(DualArrayDeque::with_capacity(0), seed, |a| {
+                a.check_invariants()
+            });
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "out of balance")]
+    fn unbalanced() {
+        // balanceを通さずにbackだけへ積むと不変条件が崩れる
+        let mut a = DualArrayDeque::with_capacity(0);
+        for c in "abcde".chars() {
+            a.back.push(c).unwrap();
+        }
+        a.check_invariants();
+    }
 }
@@ -3,18 +3,52 @@ pub mod arrayqueue;
 pub mod arraydeque;
 pub mod dualarraydeque;
 pub mod rootisharraystack;
+pub mod rootisharraydeque;
 pub mod randomqueue;
+pub mod weightedrandomqueue;
+pub mod sortedarrayset;
+pub mod binaryheap;
+#[cfg(feature = "serde")]
+mod serde_impls;
 
-use std::ops::{Index, IndexMut};
+use core::mem::MaybeUninit;
+use core::ops::Range;
 
+/// スライスと同じ形式で範囲外アクセスのpanicを起こす
+#[cold]
+#[track_caller]
+fn index_out_of_bounds(index: usize, size: usize) -> ! {
+    panic!("index out of bounds: the len is {size} but the index is {index}")
+}
+
+/// 要素を昇順に並べ替えた列を返す
+///
+/// 格納順が追加の順序や乱数で変わるコンテナを、要素の多重集合として比較やハッシュするのに使う
+fn sorted<I: IntoIterator<Item: Ord>>(iter: I) -> Vec<I::Item> {
+    let mut xs = iter.into_iter().collect::<Vec<_>>();
+    xs.sort_unstable();
+    xs
+}
+
+/// 要素が初期化されているとは限らない固定長の配列
+///
+/// どの要素が初期化済みかは`Array`を使うコンテナ側で管理する。
+/// `Option<T>`で持つ場合と違い余分な領域を使わないが、`Array`自身はdrop時に要素をdropしない。
+/// テスト時と`debug-invariants`フィーチャが有効なときは、初期化済みの位置を`init`に記録して
+/// 未初期化の位置を読まないことと、コンテナの管理と食い違わないことを確かめる
 struct Array<T> {
-    a: Box<[Option<T>]>,
+    a: Box<[MaybeUninit<T>]>,
+    #[cfg(any(test, feature = "debug-invariants"))]
+    init: Box<[bool]>,
 }
 
 impl<T> Array<T> {
     fn with_capacity(len: usize) -> Self {
-        let a = core::iter::repeat_with(|| None).take(len).collect::<Box<[_]>>();
-        Self { a }
+        Self {
+            a: Box::new_uninit_slice(len),
+            #[cfg(any(test, feature = "debug-invariants"))]
+            init: vec![false; len].into_boxed_slice(),
+        }
     }
 
     fn length(&self) -> usize {
@@ -23,18 +57,142 @@ impl<T> Array<T> {
 
     fn swap(&mut self, a: usize, b: usize) {
         self.a.swap(a, b);
+        #[cfg(any(test, feature = "debug-invariants"))]
+        self.init.swap(a, b);
+    }
+
+    /// `src`の要素を`dest`から始まる位置へまとめて移す。区間は重なってもよい
+    ///
+    /// 移した後、`src`のうち移動先と重ならない部分は未初期化として扱う
+    fn copy_within(&mut self, src: Range<usize>, dest: usize) {
+        assert!(src.start <= src.end && src.end <= self.length());
+        assert!(dest <= self.length() - src.len());
+        unsafe {
+            let p = self.a.as_mut_ptr();
+            core::ptr::copy(p.add(src.start), p.add(dest), src.len());
+        }
+        self.mark(src.clone(), false);
+        self.mark(dest..dest + src.len(), true);
+    }
+
+    /// `src`の要素を`other`の`dest`から始まる位置へまとめて移す
+    ///
+    /// 移した後、`src`は未初期化として扱う
+    fn copy_to(&mut self, src: Range<usize>, other: &mut Array<T>, dest: usize) {
+        let len = src.len();
+        self.mark(src.clone(), false);
+        other.mark(dest..dest + len, true);
+        let src = self.a[src].as_ptr();
+        let dest = other.a[dest..dest + len].as_mut_ptr();
+        unsafe { core::ptr::copy_nonoverlapping(src, dest, len) }
+    }
+
+    /// `i`番目に`x`を書き込む。元の値はdropしない
+    fn write(&mut self, i: usize, x: T) {
+        self.a[i].write(x);
+        self.mark(i..i + 1, true);
+    }
+
+    /// `i`番目の値を読み出す。以降`i`番目は未初期化として扱う
+    ///
+    /// # Safety
+    /// `i`番目が初期化済みであること
+    unsafe fn read(&mut self, i: usize) -> T {
+        self.assert_init(i..i + 1);
+        self.mark(i..i + 1, false);
+        unsafe { self.a[i].assume_init_read() }
+    }
+
+    /// `i`番目の値を`x`に置き換えて元の値を返す
+    ///
+    /// # Safety
+    /// `i`番目が初期化済みであること
+    unsafe fn replace(&mut self, i: usize, x: T) -> T {
+        core::mem::replace(unsafe { self.get_mut(i) }, x)
+    }
+
+    /// # Safety
+    /// `i`番目が初期化済みであること
+    unsafe fn get(&self, i: usize) -> &T {
+        self.assert_init(i..i + 1);
+        unsafe { self.a[i].assume_init_ref() }
     }
-}
 
-impl<T> Index<usize> for Array<T> {
-    type Output = Option<T>;
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.a[index]
+    /// # Safety
+    /// `i`番目が初期化済みであること
+    unsafe fn get_mut(&mut self, i: usize) -> &mut T {
+        self.assert_init(i..i + 1);
+        unsafe { self.a[i].assume_init_mut() }
+    }
+
+    /// # Safety
+    /// `range`の要素がすべて初期化済みであること
+    unsafe fn slice(&self, range: Range<usize>) -> &[T] {
+        self.assert_init(range.clone());
+        unsafe { self.a[range].assume_init_ref() }
+    }
+
+    /// # Safety
+    /// `range`の要素がすべて初期化済みであること
+    unsafe fn slice_mut(&mut self, range: Range<usize>) -> &mut [T] {
+        self.assert_init(range.clone());
+        unsafe { self.a[range].assume_init_mut() }
+    }
+
+    /// 重ならない2つの区間を同時に可変スライスとして返す
+    ///
+    /// # Safety
+    /// `front`と`back`の要素がすべて初期化済みで、`back`が`front`より前にあること
+    unsafe fn split_slices_mut(
+        &mut self,
+        front: Range<usize>,
+        back: Range<usize>,
+    ) -> (&mut [T], &mut [T]) {
+        self.assert_init(front.clone());
+        self.assert_init(back.clone());
+        let (left, right) = self.a.split_at_mut(front.start);
+        let front = &mut right[..front.len()];
+        let back = &mut left[back];
+        unsafe { (front.assume_init_mut(), back.assume_init_mut()) }
+    }
+
+    /// `range`の要素をdropする。以降`range`は未初期化として扱う
+    ///
+    /// # Safety
+    /// `range`の要素がすべて初期化済みであること
+    unsafe fn drop_range(&mut self, range: Range<usize>) {
+        unsafe { core::ptr::drop_in_place(self.slice_mut(range.clone())) }
+        self.mark(range, false);
     }
 }
 
-impl<T> IndexMut<usize> for Array<T> {
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.a[index]
+#[cfg(any(test, feature = "debug-invariants"))]
+impl<T> Array<T> {
+    fn mark(&mut self, range: Range<usize>, init: bool) {
+        self.init[range].fill(init);
     }
+
+    #[track_caller]
+    fn assert_init(&self, range: Range<usize>) {
+        if let Some(k) = self.init[range.clone()].iter().position(|&b| !b) {
+            panic!("slot {} is uninitialized", range.start + k);
+        }
+    }
+
+    /// 初期化済みの位置がちょうど`live`が`true`を返す位置であることを確かめる
+    #[track_caller]
+    fn check_live(&self, live: impl Fn(usize) -> bool) {
+        for (k, &init) in self.init.iter().enumerate() {
+            assert_eq!(init, live(k), "slot {k} of {}", self.length());
+        }
+    }
+}
+
+#[cfg(not(any(test, feature = "debug-invariants")))]
+impl<T> Array<T> {
+    #[inline(always)]
+    fn mark(&mut self, _range: Range<usize>, _init: bool) {}
+
+    #[inline(always)]
+    fn assert_init(&self, _range: Range<usize>) {}
 }
@@ -1,22 +1,171 @@
-use chapter1::{List, Queue};
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
 
-use crate::arraystack::ArrayStack;
+use chapter1::{Capacity, Collection, List, ListError, Queue, Stack};
 
-pub struct RandomQueue<T> {
+pub use crate::arraystack::{IntoIter, Iter, IterMut};
+use crate::{arraystack::ArrayStack, sorted};
+
+/// `RandomQueue`が取り出す要素を選ぶための乱数源
+///
+/// `fastrand::Rng`と、`FnMut(usize) -> usize`のクロージャに実装してある
+pub trait RandomSource {
+    /// `0..n`から一様に選んだ値を返す。`n`は1以上
+    ///
+    /// 範囲外の値を返すと、それを使うキューの操作がpanicする
+    fn index(&mut self, n: usize) -> usize;
+}
+
+impl RandomSource for fastrand::Rng {
+    fn index(&mut self, n: usize) -> usize {
+        self.usize(0..n)
+    }
+}
+
+impl<F: FnMut(usize) -> usize> RandomSource for F {
+    fn index(&mut self, n: usize) -> usize {
+        self(n)
+    }
+}
+
+/// `rng`で`0..n`から選ぶ。範囲外の値が返ってきたらpanicする
+///
+/// `RandomSource`は安全なトレイトなので、返す値を信用せずに使う前に確かめる
+pub(crate) fn random_index<R: RandomSource + ?Sized>(rng: &mut R, n: usize) -> usize {
+    let i = rng.index(n);
+    assert!(i < n, "RandomSource::index({n}) returned {i}");
+    i
+}
+
+/// 一様にランダムな要素を取り出すキュー
+///
+/// 乱数源`R`を指定すると、取り出す順序を再現できる。
+/// 取り出す順序は乱数源の状態で決まるので、比較やハッシュは要素を昇順に並べた列で行う
+#[derive(Clone)]
+pub struct RandomQueue<T, R = fastrand::Rng> {
     queue: ArrayStack<T>,
+    /// 次の`remove`で取り出す要素のインデックス。`peek`できるように事前に選んでおく
+    next: usize,
+    /// `sample`は`&self`から乱数を引くので`RefCell`に入れておく
+    rng: RefCell<R>,
 }
 
 impl<T> RandomQueue<T> {
+    /// 乱数の種はスレッドごとの乱数から選ぶ
     pub fn with_capacity(len: usize) -> Self {
         Self {
             queue: ArrayStack::with_capacity(len),
+            next: 0,
+            rng: RefCell::new(fastrand::Rng::new()),
+        }
+    }
+
+    /// 乱数の種を指定する。同じ種なら同じ順序で取り出される
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(fastrand::Rng::with_seed(seed))
+    }
+}
+
+impl<T, R> RandomQueue<T, R> {
+    /// 乱数源を指定する
+    pub fn with_rng(rng: R) -> Self {
+        Self {
+            queue: ArrayStack::with_capacity(0),
+            next: 0,
+            rng: RefCell::new(rng),
+        }
+    }
+
+    /// 要素を格納順に返す。`remove`で取り出される順ではない
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.queue.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.queue.iter_mut()
+    }
+
+    /// 内部の状態が不変条件を満たすかを確かめ、満たさなければpanicする
+    ///
+    /// 次に取り出す要素の位置`next`が要素を指していること
+    #[cfg(any(test, feature = "debug-invariants"))]
+    pub fn check_invariants(&self) {
+        self.queue.check_invariants();
+        let n = self.queue.size();
+        assert!(
+            self.next < n.max(1),
+            "next = {} with {n} elements",
+            self.next
+        );
+    }
+}
+
+impl<T, R: RandomSource> RandomQueue<T, R> {
+    /// 一様にランダムな要素を、取り除かずに返す
+    ///
+    /// 呼ぶたびに選び直す。次の`remove`で取り出される要素は`peek`で分かる
+    pub fn sample(&self) -> Option<&T> {
+        let n = self.queue.size();
+        if n == 0 {
+            return None;
+        }
+        let i = random_index(&mut *self.rng.borrow_mut(), n);
+        self.queue.get(i)
+    }
+
+    /// 異なる`k`個の要素をランダムな順に、取り除かずに返す
+    ///
+    /// `k`が要素数より大きい場合はすべての要素を返す
+    pub fn sample_many(&self, k: usize) -> Vec<&T> {
+        let mut xs = self.iter().collect::<Vec<_>>();
+        let k = k.min(xs.len());
+        // Fisher-Yatesのシャッフルを先頭のk個だけ行う
+        let mut rng = self.rng.borrow_mut();
+        for i in 0..k {
+            let j = i + random_index(&mut *rng, xs.len() - i);
+            xs.swap(i, j);
         }
+        xs.truncate(k);
+        xs
     }
 }
 
-impl<T> Queue<T> for RandomQueue<T> {
-    fn add(&mut self, x: T) -> Option<T> {
-        Queue::add(&mut self.queue, x)
+impl<T, R> Collection for RandomQueue<T, R> {
+    fn size(&self) -> usize {
+        self.queue.size()
+    }
+
+    fn clear(&mut self) {
+        self.queue.clear();
+        self.next = 0;
+    }
+}
+
+impl<T, R> Capacity for RandomQueue<T, R> {
+    fn capacity(&self) -> usize {
+        self.queue.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.queue.reserve(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.queue.shrink_to_fit();
+    }
+}
+
+impl<T, R: RandomSource> Queue<T> for RandomQueue<T, R> {
+    fn add(&mut self, x: T) -> Result<(), ListError<T>> {
+        self.queue.push(x)?;
+        // 追加した要素を確率1/nで選び直すことで、nextは全要素から一様に選ばれたままになる
+        let n = self.queue.size();
+        if random_index(self.rng.get_mut(), n) == 0 {
+            self.next = n - 1;
+        }
+        Ok(())
     }
 
     fn remove(&mut self) -> Option<T> {
@@ -25,19 +174,299 @@ impl<T> Queue<T> for RandomQueue<T> {
             return None;
         }
 
-        let i = fastrand::usize(0..queue_size);
+        let i = self.next;
 
-        // 末尾とスワップする
-        let x = self.queue.a[i].take();
+        // 末尾とスワップしてから末尾を取り除く
         let last_idx = queue_size - 1;
         if i != last_idx {
-            self.queue.a.swap(i, last_idx);
+            // 範囲外の位置は未初期化なので、スライスを通して範囲を確かめる
+            self.queue.as_mut_slice().swap(i, last_idx);
         }
-        self.queue.n -= 1;
-        if self.queue.a.length() >= 3 * self.queue.n {
-            self.queue.resize();
+        let x = self.queue.pop();
+        if self.queue.n > 0 {
+            self.next = random_index(self.rng.get_mut(), self.queue.n);
         }
 
         x
     }
+
+    fn peek(&self) -> Option<&T> {
+        self.queue.get(self.next)
+    }
+}
+
+impl<T, R: RandomSource + Default> FromIterator<T> for RandomQueue<T, R> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut q = Self::with_rng(R::default());
+        q.extend(iter);
+        q
+    }
+}
+
+impl<T, R: RandomSource> Extend<T> for RandomQueue<T, R> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for x in iter {
+            let added = self.add(x);
+            debug_assert!(added.is_ok());
+        }
+    }
+}
+
+impl<T, R> IntoIterator for RandomQueue<T, R> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.queue.into_iter()
+    }
+}
+
+impl<'a, T, R> IntoIterator for &'a RandomQueue<T, R> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, R> IntoIterator for &'a mut RandomQueue<T, R> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// 要素を格納順に表示する
+impl<T: fmt::Debug, R> fmt::Debug for RandomQueue<T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// 格納順や乱数源の状態によらず、同じ要素を同じ数だけ持っていれば等しい
+impl<T: Ord, R> PartialEq for RandomQueue<T, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && sorted(self.iter()) == sorted(other.iter())
+    }
+}
+
+impl<T: Ord, R> Eq for RandomQueue<T, R> {}
+
+impl<T: Ord + Hash, R> Hash for RandomQueue<T, R> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size());
+        for x in sorted(self.iter()) {
+            x.hash(state);
+        }
+    }
+}
+
+/// 昇順に並べた列を辞書式に比べる
+impl<T: Ord, R> PartialOrd for RandomQueue<T, R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord, R> Ord for RandomQueue<T, R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        sorted(self.iter()).cmp(&sorted(other.iter()))
+    }
+}
+
+impl<T, R: Default> Default for RandomQueue<T, R> {
+    fn default() -> Self {
+        Self::with_rng(R::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peek() {
+        let mut q = RandomQueue::with_capacity(0);
+        assert_eq!(q.peek(), None);
+        for x in 0..100 {
+            q.add(x).unwrap();
+        }
+
+        // peekした要素が次に取り出される
+        let mut removed = Vec::new();
+        while let Some(&x) = q.peek() {
+            assert_eq!(q.remove(), Some(x));
+            removed.push(x);
+        }
+        assert_eq!(q.remove(), None);
+
+        removed.sort();
+        assert_eq!(removed, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn bad_random_source() {
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        // 追加の間だけ正しく、その後は範囲外の`n`を返す乱数源
+        let mut calls = 0;
+        let rng = move |n: usize| {
+            calls += 1;
+            if calls <= 8 { 0 } else { n }
+        };
+        let mut q = RandomQueue::with_rng(rng);
+        for i in 0..8 {
+            q.add(format!("s{i}")).unwrap();
+        }
+
+        // 範囲外の値は使う前にpanicし、未初期化の位置は読まない
+        let err = catch_unwind(AssertUnwindSafe(|| q.remove())).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<String>().unwrap(),
+            "RandomSource::index(7) returned 7"
+        );
+        assert_eq!(q.size(), 7);
+        assert!(catch_unwind(AssertUnwindSafe(|| q.remove())).is_err());
+        assert_eq!(q.size(), 7);
+    }
+
+    #[test]
+    fn seed() {
+        // 同じ種なら同じ順序で取り出される
+        let removed = |seed| {
+            let mut q = RandomQueue::with_seed(seed);
+            q.extend(0..100);
+            core::iter::from_fn(|| q.remove()).collect::<Vec<_>>()
+        };
+        assert_eq!(removed(42), removed(42));
+        assert_ne!(removed(42), removed(43));
+
+        // 常に0を返す乱数源なら、追加のたびに追加した要素が選ばれ、取り出した後は先頭が選ばれる
+        let mut q = RandomQueue::with_rng(|_| 0);
+        q.extend(0..5);
+        let removed = core::iter::from_fn(|| q.remove()).collect::<Vec<_>>();
+        assert_eq!(removed, [4, 0, 3, 2, 1]);
+    }
+
+    #[test]
+    fn uniform() {
+        // 10個の要素から1つ取り出すことを繰り返し、カイ二乗検定で一様性を確かめる
+        const K: usize = 10;
+        const TRIALS: usize = 100_000;
+        let mut rng = fastrand::Rng::with_seed(0);
+        let mut counts = [0; K];
+        let mut second = [0; K];
+        for _ in 0..TRIALS {
+            let mut q = RandomQueue::with_rng(rng.fork());
+            q.extend(0..K);
+            counts[q.remove().unwrap()] += 1;
+            // 取り除いた後の選び直しも一様になっている
+            second[q.remove().unwrap()] += 1;
+        }
+
+        let expected = (TRIALS / K) as f64;
+        let chi2 = |counts: &[usize]| {
+            counts
+                .iter()
+                .map(|&c| (c as f64 - expected).powi(2) / expected)
+                .sum::<f64>()
+        };
+        // 自由度9のカイ二乗分布の上側0.1%点
+        assert!(chi2(&counts) < 27.88, "{counts:?}");
+        assert!(chi2(&second) < 27.88, "{second:?}");
+    }
+
+    #[test]
+    fn sample() {
+        let mut q = RandomQueue::with_seed(0);
+        assert_eq!(q.sample(), None);
+        assert!(q.sample_many(3).is_empty());
+        q.extend(0..10);
+
+        let mut seen = [false; 10];
+        for _ in 0..1000 {
+            seen[*q.sample().unwrap()] = true;
+        }
+        assert!(seen.iter().all(|&x| x));
+        assert_eq!(q.size(), 10);
+
+        // 同じ要素を2度選ばない
+        for k in [0, 1, 5, 10, 20] {
+            let mut xs = q.sample_many(k).into_iter().copied().collect::<Vec<_>>();
+            assert_eq!(xs.len(), k.min(10));
+            xs.sort();
+            xs.dedup();
+            assert_eq!(xs.len(), k.min(10));
+        }
+        assert_eq!(q.size(), 10);
+    }
+
+    #[test]
+    fn iter() {
+        let mut q = (0..10).collect::<RandomQueue<_>>();
+        q.extend(10..20);
+        q.check_invariants();
+        assert_eq!(q.iter().len(), 20);
+
+        for x in &mut q {
+            *x *= 2;
+        }
+        let mut xs = q.into_iter().collect::<Vec<_>>();
+        xs.sort();
+        assert_eq!(xs, (0..20).map(|x| x * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn clone() {
+        // 乱数源も複製されるので、同じ順序で取り出される
+        let mut q = RandomQueue::with_seed(7);
+        q.extend(0..20);
+        let mut r = q.clone();
+        assert_eq!(
+            format!("{r:?}"),
+            format!("{:?}", (0..20).collect::<Vec<_>>())
+        );
+        let removed = core::iter::from_fn(|| q.remove()).collect::<Vec<_>>();
+        assert_eq!(
+            core::iter::from_fn(|| r.remove()).collect::<Vec<_>>(),
+            removed
+        );
+        assert!(RandomQueue::<i32>::default().is_empty());
+    }
+
+    #[test]
+    fn conformance() {
+        use chapter1::testkit;
+
+        for seed in 0..4 {
+            testkit::check_queue(RandomQueue::with_seed(seed), seed, |a| a.check_invariants());
+        }
+    }
+
+    #[test]
+    fn traits() {
+        use std::hash::{BuildHasher, RandomState};
+
+        // 格納順や乱数源の状態によらず、同じ要素を同じ数だけ持っていれば等しい
+        let mut a = RandomQueue::with_seed(1);
+        a.extend([1, 2, 2, 3]);
+        let mut b = RandomQueue::with_seed(2);
+        b.extend([3, 2, 1, 2]);
+        assert_eq!(a, b);
+        let s = RandomState::new();
+        assert_eq!(s.hash_one(&a), s.hash_one(&b));
+
+        b.remove();
+        b.add(4).unwrap();
+        assert_ne!(a, b);
+        // 昇順に並べた列で比べるので、4を含む方が大きい
+        assert!(a < b);
+        assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+        assert_eq!(a.clone(), a);
+    }
 }
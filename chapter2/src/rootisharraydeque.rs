@@ -0,0 +1,172 @@
+use crate::dualarraydeque::{self, DequeHalf, DualDeque, sealed};
+use crate::rootisharraystack::{self, RootishArrayStack};
+
+/// `DualArrayDeque`のスタックを`RootishArrayStack`にした両端キュー
+///
+/// 無駄な領域はO(sqrt(n))に収まる
+pub type RootishArrayDeque<T> = DualDeque<T, RootishArrayStack<T>>;
+
+pub type Iter<'a, T> = dualarraydeque::Iter<'a, T, RootishArrayStack<T>>;
+pub type IterMut<'a, T> = dualarraydeque::IterMut<'a, T, RootishArrayStack<T>>;
+pub type IntoIter<T> = dualarraydeque::IntoIter<T, RootishArrayStack<T>>;
+
+impl<T> sealed::Sealed for RootishArrayStack<T> {}
+
+impl<T> DequeHalf<T> for RootishArrayStack<T> {
+    type Iter<'a>
+        = rootisharraystack::Iter<'a, T>
+    where
+        T: 'a;
+    type IterMut<'a>
+        = rootisharraystack::IterMut<'a, T>
+    where
+        T: 'a;
+
+    fn iter(&self) -> Self::Iter<'_> {
+        RootishArrayStack::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        RootishArrayStack::iter_mut(self)
+    }
+
+    fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        RootishArrayStack::get_mut(self, i)
+    }
+
+    fn reverse(&mut self) {
+        RootishArrayStack::reverse(self);
+    }
+
+    #[cfg(any(test, feature = "debug-invariants"))]
+    fn check_invariants(&self) {
+        RootishArrayStack::check_invariants(self);
+    }
+}
+
+impl<T> RootishArrayDeque<T> {
+    pub fn with_capacity() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RootishArrayDeque;
+    use chapter1::{Capacity, Collection, Deque, List};
+
+    #[test]
+    fn operation() {
+        let mut a = "abcd".chars().collect::<RootishArrayDeque<_>>();
+        a.add(3, 'x').unwrap();
+        a.check_invariants();
+        a.add(4, 'y').unwrap();
+        a.check_invariants();
+        assert_eq!(a.remove(0), Some('a'));
+        a.check_invariants();
+        a.add(1, 'z').unwrap();
+        a.check_invariants();
+        assert_eq!(a.set(0, 'w'), Ok('b'));
+        a.check_invariants();
+        assert_eq!(a.iter().collect::<String>(), "wzcxyd");
+        assert_eq!(a.get(6), None);
+        assert_eq!(a.remove(6), None);
+        a.check_invariants();
+    }
+
+    #[test]
+    fn deque() {
+        let mut a = RootishArrayDeque::with_capacity();
+        assert_eq!(a.remove_front(), None);
+        a.check_invariants();
+        assert_eq!(a.remove_back(), None);
+        a.check_invariants();
+        for x in 0..100 {
+            a.add_front(-x).unwrap();
+            a.check_invariants();
+            a.add_back(x).unwrap();
+            a.check_invariants();
+        }
+        assert_eq!(a.front(), Some(&-99));
+        assert_eq!(a.back(), Some(&99));
+
+        // 片側からだけ取り出しても、もう一方のスタックから移される
+        for x in (0..100).rev() {
+            assert_eq!(a.remove_back(), Some(x));
+            a.check_invariants();
+        }
+        for x in 0..100 {
+            assert_eq!(a.remove_back(), Some(-x));
+            a.check_invariants();
+        }
+        assert!(a.is_empty());
+
+        for x in 0..100 {
+            a.add_back(x).unwrap();
+            a.check_invariants();
+        }
+        for x in 0..100 {
+            assert_eq!(a.front(), Some(&x));
+            assert_eq!(a.remove_front(), Some(x));
+            a.check_invariants();
+        }
+        assert_eq!(a.front_mut(), None);
+    }
+
+    #[test]
+    fn capacity() {
+        // 無駄な領域はO(sqrt(n))
+        let mut a = (0..10_000).collect::<RootishArrayDeque<_>>();
+        for x in 0..5_000 {
+            assert_eq!(a.remove_front(), Some(x));
+        }
+        assert!(a.capacity() - a.size() <= 4 * 100);
+        a.clear();
+        a.shrink_to_fit();
+        assert_eq!(a.capacity(), 0);
+    }
+
+    #[test]
+    fn iter() {
+        let mut a = "cd".chars().collect::<RootishArrayDeque<_>>();
+        a.add_front('b').unwrap();
+        a.check_invariants();
+        a.add_front('a').unwrap();
+        a.check_invariants();
+        a.extend("ef".chars());
+        a.check_invariants();
+        assert_eq!(a.iter().collect::<String>(), "abcdef");
+        assert_eq!(a.iter().rev().collect::<String>(), "fedcba");
+        assert_eq!(a.iter().len(), 6);
+
+        for x in &mut a {
+            *x = x.to_ascii_uppercase();
+        }
+        a[0] = 'x';
+        assert_eq!(a[5], 'F');
+        let mut it = a.into_iter();
+        assert_eq!(it.next(), Some('x'));
+        assert_eq!(it.next_back(), Some('F'));
+        assert_eq!(it.collect::<String>(), "BCDE");
+    }
+
+    #[test]
+    fn conformance() {
+        use chapter1::testkit;
+
+        for seed in 0..4 {
+            testkit::check_list(RootishArrayDeque::with_capacity(), seed, |a| {
+                a.check_invariants()
+            });
+            testkit::check_deque(RootishArrayDeque::with_capacity(), seed, |a| {
+                a.check_invariants()
+            });
+            testkit::check_fifo(RootishArrayDeque::with_capacity(), seed, |a| {
+                a.check_invariants()
+            });
+            testkit::check_stack(RootishArrayDeque::with_capacity(), seed, |a| {
+                a.check_invariants()
+            });
+        }
+    }
+}
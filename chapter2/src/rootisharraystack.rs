@@ -1,10 +1,37 @@
-use chapter1::List;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::{Index, IndexMut, Range};
 
-use crate::{Array, arraystack::ArrayStack};
+use chapter1::{Capacity, Collection, List, ListError, Stack};
 
+use crate::{Array, arraystack::ArrayStack, index_out_of_bounds};
+
+/// `i2b`を表で引くインデックスの数。ブロック`0..128`の要素数で、ブロックのインデックスは`u8`に収まる
+const SMALL_INDEXES: usize = 128 * 129 / 2;
+
+/// `SMALL_INDEXES`未満のインデックスが属するブロックのインデックス
+static SMALL_I2B: [u8; SMALL_INDEXES] = {
+    let mut t = [0; SMALL_INDEXES];
+    let mut b = 0;
+    let mut i = 0;
+    while i < SMALL_INDEXES {
+        // 次のブロックの先頭に達したら進める
+        if i == (b + 1) * (b + 2) / 2 {
+            b += 1;
+        }
+        t[i] = b as u8;
+        i += 1;
+    }
+    t
+};
+
+/// `i`番目の要素は`i2bj(i)`の位置にあり、`0..n`の要素が初期化済み
 pub struct RootishArrayStack<T> {
     blocks: ArrayStack<Array<T>>,
     n: usize,
+    /// `reserve`や`clear`で確保したまま残す要素数。`shrink`はこれを収めるブロックを解放しない
+    reserved: usize,
 }
 
 impl<T> RootishArrayStack<T> {
@@ -12,143 +39,639 @@ impl<T> RootishArrayStack<T> {
         Self {
             blocks: ArrayStack::with_capacity(0),
             n: 0,
+            reserved: 0,
         }
     }
 
     /// インデックス`i`が属するブロックのインデックスを返す
+    ///
+    /// ブロック`b`の先頭は`b(b+1)/2`なので、`b(b+1) <= 2i`となる最大の`b`を求める。
+    /// 小さい`i`は表を引き、それ以外は整数の平方根から求めるので`usize`の全域で正確
     fn i2b(i: usize) -> usize {
-        let sq = 9 + 8 * i;
-        let db = (-3.0 + (sq as f64).sqrt()) / 2.0;
-        db.ceil() as usize
+        if let Some(&b) = SMALL_I2B.get(i) {
+            return b as usize;
+        }
+        let Some(i2) = i.checked_mul(2) else {
+            // 2iが溢れるほど大きいときだけ幅を広げる
+            let i2 = 2 * i as u128;
+            let s = i2.isqrt();
+            return (if s * (s + 1) <= i2 { s } else { s - 1 }) as usize;
+        };
+        // s < 2^(usize::BITS / 2)なので、s(s+1)は溢れない
+        let s = i2.isqrt();
+        if s * (s + 1) <= i2 { s } else { s - 1 }
     }
 
     /// インデックス`i`が属するブロックのインデックスと、そのブロック内でのインデックスを返す
     fn i2bj(i: usize) -> (usize, usize) {
         let b = Self::i2b(i);
-        let j = i - b * (b + 1) / 2;
+        let j = i - Self::block_start(b);
         (b, j)
     }
 
+    /// ブロック`b`の先頭のインデックス`b(b+1)/2`を返す
+    ///
+    /// `b(b+1)`が溢れても結果が収まる範囲では正しく求まるよう、偶数の方を先に2で割る
+    fn block_start(b: usize) -> usize {
+        if b.is_multiple_of(2) {
+            b / 2 * (b + 1)
+        } else {
+            b * b.div_ceil(2)
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            stack: self,
+            front: 0,
+            back: self.n,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut::new(self)
+    }
+
     fn max_size(&self) -> usize {
-        let r = self.blocks.size();
-        r * (r + 1) / 2
+        Self::block_start(self.blocks.size())
+    }
+
+    /// `m`個の要素を収めるのに必要なブロックの数
+    fn blocks_for(m: usize) -> usize {
+        m.checked_sub(1).map_or(0, |i| Self::i2b(i) + 1)
+    }
+
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        if i < self.n {
+            let (block_idx, j) = Self::i2bj(i);
+            Some(unsafe { self.blocks[block_idx].get_mut(j) })
+        } else {
+            None
+        }
     }
 
-    fn get_mut(&mut self, i: usize) -> Option<&mut Option<T>> {
+    /// `i`番目の要素を読み出す。以降`i`番目は未初期化として扱う
+    ///
+    /// # Safety
+    /// `i`番目が初期化済みであること
+    unsafe fn read(&mut self, i: usize) -> T {
         let (block_idx, j) = Self::i2bj(i);
-        match self.blocks.get_mut(block_idx) {
-            Some(Some(arr)) => Some(&mut arr[j]),
-            _ => None,
+        unsafe { self.blocks[block_idx].read(j) }
+    }
+
+    /// `i`番目に`x`を書き込む。元の値はdropしない
+    fn write(&mut self, i: usize, x: T) {
+        let (block_idx, j) = Self::i2bj(i);
+        self.blocks[block_idx].write(j, x);
+    }
+
+    /// `range`の要素をブロックごとにまとめてdropする
+    ///
+    /// # Safety
+    /// `range`の要素がすべて初期化済みであること
+    unsafe fn drop_range(&mut self, range: Range<usize>) {
+        let mut i = range.start;
+        while i < range.end {
+            let (block_idx, j) = Self::i2bj(i);
+            let len = (block_idx + 1 - j).min(range.end - i);
+            unsafe { self.blocks[block_idx].drop_range(j..j + len) };
+            i += len;
+        }
+    }
+
+    /// 要素の並びを逆にする
+    pub(crate) fn reverse(&mut self) {
+        let n = self.n;
+        for i in 0..n / 2 {
+            let x = unsafe { self.read(i) };
+            let y = unsafe { self.read(n - i - 1) };
+            self.write(i, y);
+            self.write(n - i - 1, x);
         }
     }
 
     fn grow(&mut self) {
         let block_size = self.blocks.size();
 
-        self.blocks
+        // 末尾への追加なので失敗しない
+        let added = self
+            .blocks
             .add(block_size, Array::with_capacity(block_size + 1));
+        debug_assert!(added.is_ok());
+    }
+
+    /// `m`個の要素を収められるまでブロックを足す
+    fn grow_to(&mut self, m: usize) {
+        while self.max_size() < m {
+            self.grow();
+        }
     }
 
+    /// `n`と`reserved`の大きい方を収めるブロックより2つ以上多ければ、1つだけ残して解放する
+    ///
+    /// ブロックの境界で追加と削除を繰り返しても、確保と解放を繰り返さないようにする
     fn shrink(&mut self) {
-        let mut r = self.blocks.size();
-        // (r-1)*r / 2 はブロックを1つ消した時の最大容量
-        while r > 0 && (r - 1) * r / 2 >= self.size() {
-            self.blocks.remove(r - 1);
-            r -= 1;
+        self.truncate_blocks(Self::blocks_for(self.n.max(self.reserved)) + 1);
+    }
+
+    /// 末尾のブロックを解放して`len`個以下にする
+    fn truncate_blocks(&mut self, len: usize) {
+        while self.blocks.size() > len {
+            self.blocks.pop();
+        }
+    }
+
+    /// 内部の状態が不変条件を満たすかを確かめ、満たさなければpanicする
+    ///
+    /// ブロック`b`の長さが`b + 1`で、ブロックの数が`n`個を収めるのに足りていること。
+    /// ブロックの数は`n`と`reserved`の大きい方を収める数より高々1つ多いだけ。
+    /// 初期化済みの位置は各ブロックの先頭から`0..n`に含まれる分だけ
+    #[cfg(any(test, feature = "debug-invariants"))]
+    pub fn check_invariants(&self) {
+        self.blocks.check_invariants();
+        assert!(
+            self.n <= self.max_size(),
+            "n = {} > {}",
+            self.n,
+            self.max_size()
+        );
+        let needed = Self::blocks_for(self.n.max(self.reserved));
+        assert!(
+            self.blocks.size() <= needed + 1,
+            "{} blocks for n = {}, reserved = {}",
+            self.blocks.size(),
+            self.n,
+            self.reserved
+        );
+        for (b, block) in self.blocks.iter().enumerate() {
+            assert_eq!(block.length(), b + 1, "length of block {b}");
+            let live = self.n.saturating_sub(Self::block_start(b)).min(b + 1);
+            block.check_live(|k| k < live);
         }
     }
 }
 
-impl<T> List<T> for RootishArrayStack<T> {
+impl<T> Collection for RootishArrayStack<T> {
     fn size(&self) -> usize {
         self.n
     }
 
-    fn get(&self, i: usize) -> Option<&T> {
-        let (block_idx, j) = Self::i2bj(i);
+    fn clear(&mut self) {
+        // dropがpanicしても二重にdropしないよう、先に要素数を0にする
+        let n = core::mem::replace(&mut self.n, 0);
+        // 容量はそのまま残す
+        self.reserved = self.reserved.max(n);
+        unsafe { self.drop_range(0..n) }
+    }
+}
 
-        self.blocks
-            .get(block_idx)
-            .and_then(|block| block[j].as_ref())
+impl<T> Drop for RootishArrayStack<T> {
+    fn drop(&mut self) {
+        self.clear();
     }
+}
 
-    fn set(&mut self, i: usize, x: T) -> Option<T> {
-        let (block_idx, j) = Self::i2bj(i);
-        match self.blocks.get_mut(block_idx) {
-            Some(Some(arr)) => arr[j].replace(x),
-            _ => Some(x),
+impl<T> Capacity for RootishArrayStack<T> {
+    fn capacity(&self) -> usize {
+        self.max_size()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.reserved = self.reserved.max(self.n + additional);
+        self.grow_to(self.n + additional);
+    }
+
+    /// 予備に残している空のブロックも解放する
+    fn shrink_to_fit(&mut self) {
+        self.reserved = 0;
+        self.truncate_blocks(Self::blocks_for(self.n));
+        self.blocks.shrink_to_fit();
+    }
+}
+
+impl<T> List<T> for RootishArrayStack<T> {
+    fn get(&self, i: usize) -> Option<&T> {
+        if i < self.n {
+            let (block_idx, j) = Self::i2bj(i);
+            Some(unsafe { self.blocks[block_idx].get(j) })
+        } else {
+            None
         }
     }
 
-    fn add(&mut self, i: usize, x: T) -> Option<T> {
+    fn set(&mut self, i: usize, x: T) -> Result<T, ListError<T>> {
+        if i >= self.size() {
+            return Err(ListError::OutOfBounds {
+                index: i,
+                size: self.size(),
+                value: x,
+            });
+        }
+
+        let (block_idx, j) = Self::i2bj(i);
+        Ok(unsafe { self.blocks[block_idx].replace(j, x) })
+    }
+
+    fn add(&mut self, i: usize, x: T) -> Result<(), ListError<T>> {
+        if i > self.size() {
+            return Err(ListError::OutOfBounds {
+                index: i,
+                size: self.size(),
+                value: x,
+            });
+        }
+
         if self.max_size() < self.size() + 1 {
             self.grow();
         }
-        self.n += 1;
-        for j in (i..(self.size() - 1)).rev() {
-            // Swap j and j+1 to shift right
-            // 1度に2つ可変参照をsafeなRustでは取れないのでこうなっている
-            let a = self.get_mut(j).and_then(|v| v.take());
-            match self.get_mut(j + 1) {
-                Some(dst) => {
-                    *dst = a;
-                }
-                None => {}
+        // i..nを右に1つずらす。後ろのブロックから順に、ブロック内はまとめて移し、
+        // 1つ前のブロックの末尾の要素をブロックの先頭へ繰り越す
+        let (bi, ji) = Self::i2bj(i);
+        let (bn, jn) = Self::i2bj(self.n);
+        for b in (bi..=bn).rev() {
+            let start = if b == bi { ji } else { 0 };
+            let end = if b == bn { jn } else { b };
+            self.blocks[b].copy_within(start..end, start + 1);
+            if b > bi {
+                let y = unsafe { self.blocks[b - 1].read(b - 1) };
+                self.blocks[b].write(0, y);
             }
         }
-        self.set(i, x)
+        self.blocks[bi].write(ji, x);
+        self.n += 1;
+        Ok(())
     }
 
     fn remove(&mut self, i: usize) -> Option<T> {
-        let x = match self.get_mut(i) {
-            Some(val) => val.take(),
-            None => return None,
-        };
-        for j in i..(self.size() - 1) {
-            // Swap j and j+1 to shift left
-            let a = self.get_mut(j + 1).and_then(|v| v.take());
-            match self.get_mut(j) {
-                Some(dst) => {
-                    *dst = a;
-                }
-                None => {}
+        if i >= self.size() {
+            return None;
+        }
+
+        let x = unsafe { self.read(i) };
+        // i+1..nを左に1つずらす。前のブロックから順に、ブロック内はまとめて移し、
+        // 次のブロックの先頭の要素をブロックの末尾へ繰り越す
+        let (bi, ji) = Self::i2bj(i);
+        let (bl, jl) = Self::i2bj(self.n - 1);
+        for b in bi..=bl {
+            let start = if b == bi { ji } else { 0 };
+            let end = if b == bl { jl } else { b };
+            self.blocks[b].copy_within(start + 1..end + 1, start);
+            if b < bl {
+                let y = unsafe { self.blocks[b + 1].read(0) };
+                self.blocks[b].write(b, y);
             }
         }
         self.n -= 1;
 
         self.shrink();
-        x
+        Some(x)
+    }
+}
+
+impl<T> Stack<T> for RootishArrayStack<T> {
+    fn push(&mut self, x: T) -> Result<(), ListError<T>> {
+        List::add(self, self.size(), x)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        List::remove(self, self.size().checked_sub(1)?)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.get(self.size().checked_sub(1)?)
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut T> {
+        let i = self.size().checked_sub(1)?;
+        self.get_mut(i)
+    }
+}
+
+impl<T> Index<usize> for RootishArrayStack<T> {
+    type Output = T;
+
+    #[track_caller]
+    fn index(&self, i: usize) -> &Self::Output {
+        match self.get(i) {
+            Some(x) => x,
+            None => index_out_of_bounds(i, self.size()),
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for RootishArrayStack<T> {
+    #[track_caller]
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
+        let size = self.size();
+        match self.get_mut(i) {
+            Some(x) => x,
+            None => index_out_of_bounds(i, size),
+        }
+    }
+}
+
+impl<T> FromIterator<T> for RootishArrayStack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::with_capacity();
+        a.extend(iter);
+        a
+    }
+}
+
+impl<T> Extend<T> for RootishArrayStack<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        // `reserve`と違い、要素を取り除けば先に足したブロックも解放する
+        self.grow_to(self.n + iter.size_hint().0);
+        for x in iter {
+            let added = self.push(x);
+            debug_assert!(added.is_ok());
+        }
+    }
+}
+
+impl<T> IntoIterator for RootishArrayStack<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        // 取り出していない要素はIntoIterがdropする
+        let back = core::mem::replace(&mut self.n, 0);
+        IntoIter {
+            stack: self,
+            front: 0,
+            back,
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a RootishArrayStack<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut RootishArrayStack<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: Clone> Clone for RootishArrayStack<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RootishArrayStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// ブロックの数によらず、要素の列が等しければ等しい
+impl<T: PartialEq> PartialEq for RootishArrayStack<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for RootishArrayStack<T> {}
+
+impl<T: Hash> Hash for RootishArrayStack<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size());
+        for x in self.iter() {
+            x.hash(state);
+        }
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for RootishArrayStack<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for RootishArrayStack<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T> Default for RootishArrayStack<T> {
+    fn default() -> Self {
+        Self::with_capacity()
+    }
+}
+
+/// 要素への参照を先頭から順に返すイテレータ
+pub struct Iter<'a, T> {
+    stack: &'a RootishArrayStack<T>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            self.front += 1;
+            self.stack.get(self.front - 1)
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            self.back -= 1;
+            self.stack.get(self.back)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+/// 要素への可変参照を先頭から順に返すイテレータ
+///
+/// 最後のブロックは使っている部分だけを`back`に切り出しておき、残りのブロックを順に辿る
+pub struct IterMut<'a, T> {
+    blocks: core::slice::IterMut<'a, Array<T>>,
+    front: core::slice::IterMut<'a, T>,
+    back: core::slice::IterMut<'a, T>,
+}
+
+impl<'a, T> IterMut<'a, T> {
+    fn new(stack: &'a mut RootishArrayStack<T>) -> Self {
+        let Some(last) = stack.n.checked_sub(1) else {
+            return Self {
+                blocks: Default::default(),
+                front: Default::default(),
+                back: Default::default(),
+            };
+        };
+        let (b, j) = RootishArrayStack::<T>::i2bj(last);
+        let (last_block, blocks) = stack.blocks.as_mut_slice()[..=b]
+            .split_last_mut()
+            .expect("0..=bのブロックは必ず存在する");
+        Self {
+            back: unsafe { last_block.slice_mut(0..j + 1) }.iter_mut(),
+            blocks: blocks.iter_mut(),
+            front: Default::default(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(x) = self.front.next() {
+                return Some(x);
+            }
+            match self.blocks.next() {
+                // 最後以外のブロックはすべて埋まっている
+                Some(block) => {
+                    self.front = unsafe { block.slice_mut(0..block.length()) }.iter_mut()
+                }
+                None => return self.back.next(),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let blocks = self
+            .blocks
+            .as_slice()
+            .iter()
+            .map(Array::length)
+            .sum::<usize>();
+        let len = self.front.len() + blocks + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(x) = self.back.next_back() {
+                return Some(x);
+            }
+            match self.blocks.next_back() {
+                Some(block) => self.back = unsafe { block.slice_mut(0..block.length()) }.iter_mut(),
+                None => return self.front.next_back(),
+            }
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+/// 要素を先頭から順に取り出すイテレータ
+///
+/// `stack`の要素数は0にしてあり、`front..back`がまだ取り出していない要素
+pub struct IntoIter<T> {
+    stack: RootishArrayStack<T>,
+    front: usize,
+    back: usize,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            self.front += 1;
+            Some(unsafe { self.stack.read(self.front - 1) })
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            self.back -= 1;
+            Some(unsafe { self.stack.read(self.back) })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        let range = self.front..self.back;
+        self.front = self.back;
+        unsafe { self.stack.drop_range(range) }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use chapter1::List;
+    use chapter1::{Capacity, Collection, List, Stack};
 
     #[test]
     fn operation() {
         // Initialize
         let mut a = super::RootishArrayStack::with_capacity();
-        a.add(0, 'b');
-        a.add(1, 'r');
-        a.add(2, 'e');
-        a.add(3, 'd');
+        a.add(0, 'b').unwrap();
+        a.check_invariants();
+        a.add(1, 'r').unwrap();
+        a.check_invariants();
+        a.add(2, 'e').unwrap();
+        a.check_invariants();
+        a.add(3, 'd').unwrap();
+        a.check_invariants();
 
         // Add
-        a.add(2, 'e');
-        a.add(5, 'r');
-        a.add(5, 'e');
+        a.add(2, 'e').unwrap();
+        a.check_invariants();
+        a.add(5, 'r').unwrap();
+        a.check_invariants();
+        a.add(5, 'e').unwrap();
+        a.check_invariants();
 
         // remove
         let x = a.remove(4);
         assert_eq!(x, Some('d'));
+        a.check_invariants();
         let x = a.remove(4);
         assert_eq!(x, Some('e'));
+        a.check_invariants();
         let x = a.remove(4);
         assert_eq!(x, Some('r'));
+        a.check_invariants();
 
         // set
-        a.set(2, 'i');
+        a.set(2, 'i').unwrap();
+        a.check_invariants();
 
         // check
         assert_eq!(a.get(0), Some(&'b'));
@@ -156,4 +679,258 @@ mod tests {
         assert_eq!(a.get(2), Some(&'i'));
         assert_eq!(a.get(3), Some(&'e'));
     }
+
+    #[test]
+    fn stack() {
+        let mut a = super::RootishArrayStack::with_capacity();
+        assert_eq!(a.pop(), None);
+        a.check_invariants();
+        assert_eq!(a.peek(), None);
+        assert_eq!(a.peek_mut(), None);
+
+        for c in "abc".chars() {
+            a.push(c).unwrap();
+            a.check_invariants();
+        }
+        assert_eq!(a.peek(), Some(&'c'));
+        if let Some(x) = a.peek_mut() {
+            *x = 'x';
+        }
+        assert_eq!(a.pop(), Some('x'));
+        a.check_invariants();
+        assert_eq!(a.pop(), Some('b'));
+        a.check_invariants();
+        assert_eq!(a.pop(), Some('a'));
+        a.check_invariants();
+        assert_eq!(a.pop(), None);
+        a.check_invariants();
+        assert_eq!(a.size(), 0);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut a = super::RootishArrayStack::with_capacity();
+        a.reserve(10);
+        // 1+2+3+4 = 10
+        assert_eq!(a.capacity(), 10);
+        for c in "abcdefg".chars() {
+            a.push(c).unwrap();
+        }
+        assert_eq!(a.capacity(), 10);
+
+        a.shrink_to_fit();
+        assert_eq!(a.capacity(), 10);
+        a.pop();
+        a.shrink_to_fit();
+        assert_eq!(a.capacity(), 6);
+        assert_eq!(a.peek(), Some(&'f'));
+
+        a.clear();
+        a.check_invariants();
+        assert!(a.is_empty());
+        assert_eq!(a.get(0), None);
+        a.push('z').unwrap();
+        assert_eq!(a.get(0), Some(&'z'));
+
+        // 予約した分は要素を取り除いても解放しない
+        let mut a = (0..3).collect::<super::RootishArrayStack<_>>();
+        a.reserve(18);
+        assert_eq!(a.capacity(), 21);
+        a.pop();
+        a.check_invariants();
+        assert_eq!(a.capacity(), 21);
+        a.shrink_to_fit();
+        a.check_invariants();
+        assert_eq!(a.capacity(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "4 blocks for n = 3, reserved = 0")]
+    fn too_many_blocks() {
+        // 予備のブロックは1つまで
+        let mut a = (0..3).collect::<super::RootishArrayStack<_>>();
+        a.grow();
+        a.check_invariants();
+        a.grow();
+        a.check_invariants();
+    }
+
+    #[test]
+    fn iter() {
+        let mut a = "abcdefg".chars().collect::<super::RootishArrayStack<_>>();
+        a.extend("hi".chars());
+        a.check_invariants();
+        assert_eq!(a.iter().collect::<String>(), "abcdefghi");
+        assert_eq!(a.iter().rev().collect::<String>(), "ihgfedcba");
+        assert_eq!(a.iter().len(), 9);
+
+        // 最後のブロックの空きは辿らない
+        let mut it = a.iter_mut();
+        assert_eq!(it.len(), 9);
+        assert_eq!(it.next_back(), Some(&mut 'i'));
+        assert_eq!(it.next(), Some(&mut 'a'));
+        assert_eq!(it.len(), 7);
+        for x in &mut a {
+            *x = x.to_ascii_uppercase();
+        }
+        assert_eq!(
+            a.iter_mut().rev().map(|x| *x).collect::<String>(),
+            "IHGFEDCBA"
+        );
+
+        let mut it = a.into_iter();
+        assert_eq!(it.next(), Some('A'));
+        assert_eq!(it.next_back(), Some('I'));
+        assert_eq!(it.len(), 7);
+        assert_eq!(it.collect::<String>(), "BCDEFGH");
+    }
+
+    #[test]
+    fn index() {
+        let mut a = "abcd".chars().collect::<super::RootishArrayStack<_>>();
+        assert_eq!(a[0], 'a');
+        assert_eq!(a[3], 'd');
+        a[1] = 'x';
+        if let Some(x) = a.get_mut(2) {
+            *x = 'y';
+        }
+        assert_eq!(a.get_mut(4), None);
+        assert_eq!(a.iter().collect::<String>(), "axyd");
+    }
+
+    #[test]
+    fn shift_across_blocks() {
+        // ブロックの境界をまたぐすべての位置への挿入と削除をVecと比べる
+        for n in [0, 1, 3, 6, 10, 14, 15, 21] {
+            for i in 0..=n {
+                let mut a = (0..n).collect::<super::RootishArrayStack<_>>();
+                let mut v = (0..n).collect::<Vec<_>>();
+                a.add(i, 100).unwrap();
+                v.insert(i, 100);
+                assert!(a.iter().eq(v.iter()));
+                assert_eq!(a.remove(i), Some(v.remove(i)));
+                if i < n {
+                    assert_eq!(a.remove(i), Some(v.remove(i)));
+                }
+                assert!(a.iter().eq(v.iter()));
+            }
+        }
+    }
+
+    #[test]
+    fn spare_block() {
+        // 1+2+3 = 6
+        let mut a = (0..6).collect::<super::RootishArrayStack<_>>();
+        assert_eq!(a.capacity(), 6);
+        a.push(6).unwrap();
+        assert_eq!(a.capacity(), 10);
+        // 空になったブロックは1つまで残す
+        a.pop();
+        assert_eq!(a.capacity(), 10);
+        a.pop();
+        assert_eq!(a.capacity(), 10);
+        a.pop();
+        a.pop();
+        assert_eq!(a.capacity(), 6);
+        a.shrink_to_fit();
+        assert_eq!(a.capacity(), 3);
+    }
+
+    type R = super::RootishArrayStack<()>;
+
+    /// `i2bj(i)`がブロックの範囲に収まっているかを、溢れないようu128で確かめる
+    fn check_i2bj(i: usize) {
+        let block_start = |b: u128| b * (b + 1) / 2;
+        let (b, j) = R::i2bj(i);
+        let start = block_start(b as u128);
+        assert!(start <= i as u128, "i = {i}");
+        assert!((i as u128) < block_start(b as u128 + 1), "i = {i}");
+        assert_eq!(j as u128, i as u128 - start, "i = {i}");
+    }
+
+    #[test]
+    fn i2bj_small() {
+        // 先頭から順にすべてのインデックスを調べる
+        let mut i = 0;
+        for b in 0..2000 {
+            for j in 0..=b {
+                assert_eq!(R::i2bj(i), (b, j));
+                i += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn i2bj_block_boundaries() {
+        // 2の冪の前後のブロックと、usizeの上限に収まる最後のブロックの境界を調べる
+        // usizeの幅から求めるので、32bitの環境でも上限付近まで調べられる
+        let last_block = ((2 * usize::MAX as u128).isqrt()) as usize;
+        let blocks = (0..usize::BITS / 2 + 1)
+            .flat_map(|k| (1usize << k).saturating_sub(2)..=(1 << k) + 2)
+            .chain(last_block - 3..=last_block + 1);
+        for b in blocks {
+            let start = b as u128 * (b as u128 + 1) / 2;
+            for i in [start.saturating_sub(1), start, start + b as u128] {
+                if let Ok(i) = usize::try_from(i) {
+                    check_i2bj(i);
+                }
+            }
+        }
+        check_i2bj(usize::MAX);
+        check_i2bj(usize::MAX / 2);
+        check_i2bj(usize::MAX / 2 + 1);
+    }
+
+    #[test]
+    fn i2bj_random() {
+        // 桁の大きさが偏らないよう、ランダムな値をランダムな幅だけ右シフトする
+        let mut rng = fastrand::Rng::with_seed(0);
+        for _ in 0..100_000 {
+            let i = rng.usize(..) >> rng.u32(0..usize::BITS);
+            check_i2bj(i);
+        }
+    }
+
+    #[test]
+    fn drop_elements() {
+        use std::rc::Rc;
+
+        // 取り除いた要素も残った要素も、ちょうど1回ずつdropされる
+        let x = Rc::new(());
+        let mut a = (0..20)
+            .map(|_| x.clone())
+            .collect::<super::RootishArrayStack<_>>();
+        a.remove(3);
+        a.add(5, x.clone()).unwrap();
+        assert_eq!(Rc::strong_count(&x), 21);
+        let mut it = a.into_iter();
+        it.next();
+        it.next_back();
+        assert_eq!(Rc::strong_count(&x), 19);
+        drop(it);
+        assert_eq!(Rc::strong_count(&x), 1);
+
+        let mut a = (0..20)
+            .map(|_| x.clone())
+            .collect::<super::RootishArrayStack<_>>();
+        a.clear();
+        assert_eq!(Rc::strong_count(&x), 1);
+        a.extend((0..5).map(|_| x.clone()));
+        drop(a);
+        assert_eq!(Rc::strong_count(&x), 1);
+    }
+
+    #[test]
+    fn conformance() {
+        use chapter1::testkit;
+
+        for seed in 0..4 {
+            testkit::check_list(super::RootishArrayStack::with_capacity(), seed, |a| {
+                a.check_invariants()
+            });
+            testkit::check_stack(super::RootishArrayStack::with_capacity(), seed, |a| {
+                a.check_invariants()
+            });
+        }
+    }
 }
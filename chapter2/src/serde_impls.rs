@@ -0,0 +1,296 @@
+//! `serde`フィーチャで有効になる`Serialize`/`Deserialize`の実装
+//!
+//! どのコンテナも先頭からの要素の列として読み書きするので、配列長や環状配列の先頭位置などの
+//! 内部の配置は保存されず、同じ列を持つ`Vec`とも互いに読み書きできる。
+//! ただし`ArrayQueue`は容量固定かどうかも残すため、`bound`と`items`を持つ構造体として読み書きする
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use chapter1::Capacity;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::arraydeque::ArrayDeque;
+use crate::arrayqueue::{ArrayQueue, Overflow};
+use crate::arraystack::ArrayStack;
+use crate::dualarraydeque::DualArrayDeque;
+use crate::randomqueue::{RandomQueue, RandomSource};
+use crate::rootisharraydeque::RootishArrayDeque;
+use crate::rootisharraystack::RootishArrayStack;
+
+/// 列の長さとして申告された値を信用して確保する上限
+///
+/// 壊れた入力が巨大な長さを申告しても、読む前に大きな領域を確保しないようにする
+const MAX_PREALLOCATION: usize = 4096;
+
+/// 読み出した要素を1回の`Extend`でまとめて末尾に加えて`C`を組み立てる
+///
+/// 要素ごとに`extend`を呼ぶと、実装によってはそのたびに`reserve`で再確保が起きる
+struct SeqVisitor<C, T> {
+    marker: PhantomData<fn() -> (C, T)>,
+}
+
+impl<'de, C, T> Visitor<'de> for SeqVisitor<C, T>
+where
+    C: Default + Capacity + Extend<T>,
+    T: Deserialize<'de>,
+{
+    type Value = C;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<C, A::Error> {
+        let mut c = C::default();
+        c.reserve(seq.size_hint().unwrap_or(0).min(MAX_PREALLOCATION));
+        // 途中で読み出しに失敗したら、そこで`extend`を打ち切ってエラーを返す
+        let mut error = None;
+        c.extend(core::iter::from_fn(|| {
+            seq.next_element().unwrap_or_else(|e| {
+                error = Some(e);
+                None
+            })
+        }));
+        match error {
+            Some(e) => Err(e),
+            None => Ok(c),
+        }
+    }
+}
+
+fn deserialize_seq<'de, C, T, D>(deserializer: D) -> Result<C, D::Error>
+where
+    C: Default + Capacity + Extend<T>,
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_seq(SeqVisitor {
+        marker: PhantomData,
+    })
+}
+
+macro_rules! impl_serde_seq {
+    ($($ty:ident),*) => {$(
+        impl<T: Serialize> Serialize for $ty<T> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.iter())
+            }
+        }
+
+        impl<'de, T: Deserialize<'de>> Deserialize<'de> for $ty<T> {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserialize_seq(deserializer)
+            }
+        }
+    )*};
+}
+
+impl_serde_seq!(
+    ArrayStack,
+    ArrayDeque,
+    DualArrayDeque,
+    RootishArrayStack,
+    RootishArrayDeque
+);
+
+/// `ArrayQueue`を書き出すときの形式
+#[derive(serde::Serialize)]
+#[serde(rename = "ArrayQueue")]
+struct QueueRef<'a, T> {
+    bound: Option<(usize, Overflow)>,
+    items: Items<'a, T>,
+}
+
+/// `ArrayQueue`を読み込むときの形式
+#[derive(serde::Deserialize)]
+#[serde(rename = "ArrayQueue")]
+struct QueueData<T> {
+    bound: Option<(usize, Overflow)>,
+    items: Vec<T>,
+}
+
+/// キューの要素を取り出される順の列として書き出す
+struct Items<'a, T>(&'a ArrayQueue<T>);
+
+impl<T: Serialize> Serialize for Items<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+/// 容量固定なら容量と満杯時の振る舞いも書き出す
+impl<T: Serialize> Serialize for ArrayQueue<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        QueueRef {
+            bound: self.bound,
+            items: Items(self),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// 容量固定のキューで、要素数が容量を超えていたり配列長が`usize`に収まらなかったりする入力はエラーにする。
+/// 申告された容量は先に確保せず、読み込んだ要素の分だけ確保して、以降は容量まで必要に応じて伸ばす
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for ArrayQueue<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = QueueData::deserialize(deserializer)?;
+        let mut q = match data.bound {
+            None => ArrayQueue::with_capacity(data.items.len() + 1),
+            Some((capacity, overflow)) => {
+                if capacity.checked_next_power_of_two().is_none() {
+                    return Err(de::Error::invalid_value(
+                        de::Unexpected::Unsigned(capacity as u64),
+                        &"a capacity whose power-of-two array length fits in usize",
+                    ));
+                }
+                if data.items.len() > capacity {
+                    return Err(de::Error::invalid_length(
+                        data.items.len(),
+                        &"at most as many items as the capacity",
+                    ));
+                }
+                ArrayQueue::bounded_lazily(capacity, overflow, data.items.len())
+            }
+        };
+        q.extend(data.items);
+        Ok(q)
+    }
+}
+
+/// 要素を格納順に書き出す。乱数源の状態は保存しない
+impl<T: Serialize, R> Serialize for RandomQueue<T, R> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// 乱数源は`R::default()`で作り直す
+impl<'de, T: Deserialize<'de>, R: RandomSource + Default> Deserialize<'de> for RandomQueue<T, R> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_seq(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chapter1::{Deque, Queue};
+
+    use super::*;
+
+    /// JSONと`postcard`の両方で書き出して読み戻す
+    fn round_trip<C>(c: &C) -> (C, C)
+    where
+        C: Serialize + for<'de> Deserialize<'de>,
+    {
+        let json = serde_json::to_string(c).unwrap();
+        let bytes = postcard::to_allocvec(c).unwrap();
+        (
+            serde_json::from_str(&json).unwrap(),
+            postcard::from_bytes(&bytes).unwrap(),
+        )
+    }
+
+    #[test]
+    fn sequences() {
+        let a = (0..10).collect::<ArrayStack<_>>();
+        assert_eq!(round_trip(&a), (a.clone(), a.clone()));
+        assert_eq!(serde_json::to_string(&a).unwrap(), "[0,1,2,3,4,5,6,7,8,9]");
+
+        // 環状配列の先頭位置によらず、先頭からの列として書き出す
+        let mut d = ArrayDeque::with_capacity(4);
+        for x in [2, 3] {
+            d.add_back(x).unwrap();
+        }
+        for x in [1, 0] {
+            d.add_front(x).unwrap();
+        }
+        assert_eq!(serde_json::to_string(&d).unwrap(), "[0,1,2,3]");
+        assert_eq!(round_trip(&d), (d.clone(), d.clone()));
+
+        let mut dual = (0..5).collect::<DualArrayDeque<_>>();
+        dual.add_front(-1).unwrap();
+        assert_eq!(round_trip(&dual), (dual.clone(), dual.clone()));
+
+        let r = (0..100).collect::<RootishArrayStack<_>>();
+        assert_eq!(round_trip(&r), (r.clone(), r.clone()));
+        let r = (0..100).collect::<RootishArrayDeque<_>>();
+        assert_eq!(round_trip(&r), (r.clone(), r.clone()));
+
+        // 空の列や、`Vec`として書き出したものも読める
+        let empty = ArrayDeque::<u8>::default();
+        assert_eq!(round_trip(&empty), (empty.clone(), empty.clone()));
+        let v = serde_json::to_string(&vec![1, 2, 3]).unwrap();
+        let dual: DualArrayDeque<i32> = serde_json::from_str(&v).unwrap();
+        assert_eq!(dual, (1..4).collect());
+    }
+
+    #[test]
+    fn array_queue() {
+        // 容量固定のキューは読み込んでも同じ容量と振る舞いのまま
+        let mut q = ArrayQueue::bounded(3, Overflow::Overwrite);
+        q.extend(["a", "b", "c", "d"].map(String::from));
+        assert_eq!(
+            serde_json::to_string(&q).unwrap(),
+            r#"{"bound":[3,"Overwrite"],"items":["b","c","d"]}"#
+        );
+        let (mut json, bytes) = round_trip(&q);
+        assert_eq!(json, q);
+        assert_eq!(bytes, q);
+        assert_eq!(bytes.bound, q.bound);
+        json.add("e".to_string()).unwrap();
+        json.check_invariants();
+        assert_eq!(json.iter().collect::<Vec<_>>(), ["c", "d", "e"]);
+
+        let q = (0..5).collect::<ArrayQueue<_>>();
+        assert_eq!(
+            serde_json::to_string(&q).unwrap(),
+            r#"{"bound":null,"items":[0,1,2,3,4]}"#
+        );
+        let (json, bytes) = round_trip(&q);
+        assert_eq!((json.bound, bytes.bound), (None, None));
+        assert_eq!((json, bytes), (q.clone(), q));
+
+        // 容量を超える要素や、配列長が`usize`に収まらない容量は読み込まない
+        let too_many = r#"{"bound":[2,"Reject"],"items":[1,2,3]}"#;
+        assert!(serde_json::from_str::<ArrayQueue<i32>>(too_many).is_err());
+        let too_large = format!(r#"{{"bound":[{},"Reject"],"items":[]}}"#, usize::MAX);
+        assert!(serde_json::from_str::<ArrayQueue<i32>>(&too_large).is_err());
+
+        // 巨大な容量を申告されても、その分を先に確保しない。確保すると8TiBを超えて異常終了する
+        let huge = r#"{"bound":[1099511627776,"Reject"],"items":[1,2,3]}"#;
+        let mut q = serde_json::from_str::<ArrayQueue<i32>>(huge).unwrap();
+        q.check_invariants();
+        assert_eq!(q.capacity(), 1 << 40);
+        q.extend(4..100);
+        q.check_invariants();
+        assert!(q.iter().copied().eq(1..100));
+    }
+
+    #[test]
+    fn random_queue() {
+        let mut q = RandomQueue::with_seed(0);
+        q.extend(0..20);
+        q.remove();
+
+        let (mut json, bytes): (RandomQueue<i32>, RandomQueue<i32>) = round_trip(&q);
+        assert!(json.iter().eq(q.iter()));
+        assert!(bytes.iter().eq(q.iter()));
+
+        // 読み込んだキューからもすべての要素を取り出せる
+        let mut removed = core::iter::from_fn(|| json.remove()).collect::<Vec<_>>();
+        removed.sort();
+        let mut expected = q.into_iter().collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(removed, expected);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(serde_json::from_str::<ArrayStack<i32>>("{}").is_err());
+        assert!(serde_json::from_str::<ArrayDeque<i32>>("[1, \"a\"]").is_err());
+        // 申告された長さより要素が少ない
+        assert!(postcard::from_bytes::<ArrayStack<u8>>(&[200, 1, 1]).is_err());
+    }
+}
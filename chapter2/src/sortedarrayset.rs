@@ -0,0 +1,218 @@
+use core::fmt;
+use core::ops::{Bound, RangeBounds};
+
+use chapter1::{Capacity, Collection, List, SSet};
+
+use crate::arraystack::ArrayStack;
+
+/// 昇順に並べた`ArrayStack`による`SSet`
+///
+/// 探索は二分探索でO(log n)、追加・削除は要素のシフトがあるのでO(n)
+///
+/// 比較やハッシュは昇順に並べた要素の列で行う。
+/// `Ord`を実装すると`s.min()`が`SSet::min`ではなく`Ord::min`に解決されてしまうので、`PartialOrd`までにしている
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd)]
+pub struct SortedArraySet<T> {
+    a: ArrayStack<T>,
+}
+
+impl<T: Ord> SortedArraySet<T> {
+    pub fn with_capacity(len: usize) -> Self {
+        Self {
+            a: ArrayStack::with_capacity(len),
+        }
+    }
+
+    /// `x`以上の最小の要素のインデックスを返す
+    fn lower_bound(&self, x: &T) -> usize {
+        self.partition_point(|y| y < x)
+    }
+
+    /// `x`より大きい最小の要素のインデックスを返す
+    fn upper_bound(&self, x: &T) -> usize {
+        self.partition_point(|y| y <= x)
+    }
+
+    /// `pred`が`true`となる先頭部分の長さを二分探索で求める
+    fn partition_point<P>(&self, mut pred: P) -> usize
+    where
+        P: FnMut(&T) -> bool,
+    {
+        let (mut lo, mut hi) = (0, self.a.size());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.a.get(mid) {
+                Some(y) if pred(y) => lo = mid + 1,
+                _ => hi = mid,
+            }
+        }
+        lo
+    }
+
+    /// 内部の状態が不変条件を満たすかを確かめ、満たさなければpanicする
+    ///
+    /// 要素が狭義の昇順に並んでいること
+    #[cfg(any(test, feature = "debug-invariants"))]
+    pub fn check_invariants(&self) {
+        self.a.check_invariants();
+        let xs = self.a.as_slice();
+        if let Some(i) = (1..xs.len()).find(|&i| xs[i - 1] >= xs[i]) {
+            panic!("elements {} and {i} are not in ascending order", i - 1);
+        }
+    }
+}
+
+impl<T> Collection for SortedArraySet<T> {
+    fn size(&self) -> usize {
+        self.a.size()
+    }
+
+    fn clear(&mut self) {
+        self.a.clear();
+    }
+}
+
+impl<T> Capacity for SortedArraySet<T> {
+    fn capacity(&self) -> usize {
+        self.a.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.a.reserve(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.a.shrink_to_fit();
+    }
+}
+
+impl<T: Ord> SSet<T> for SortedArraySet<T> {
+    fn add(&mut self, x: T) -> bool {
+        let i = self.lower_bound(&x);
+        if self.a.get(i) == Some(&x) {
+            return false;
+        }
+        self.a.add(i, x).is_ok()
+    }
+
+    fn remove(&mut self, x: &T) -> Option<T> {
+        let i = self.lower_bound(x);
+        if self.a.get(i) == Some(x) {
+            self.a.remove(i)
+        } else {
+            None
+        }
+    }
+
+    fn find(&self, x: &T) -> Option<&T> {
+        self.a.get(self.lower_bound(x))
+    }
+
+    fn predecessor(&self, x: &T) -> Option<&T> {
+        self.lower_bound(x)
+            .checked_sub(1)
+            .and_then(|i| self.a.get(i))
+    }
+
+    fn min(&self) -> Option<&T> {
+        self.a.get(0)
+    }
+
+    fn max(&self) -> Option<&T> {
+        self.size().checked_sub(1).and_then(|i| self.a.get(i))
+    }
+
+    fn range<'a, R>(&'a self, range: R) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a,
+        R: RangeBounds<T>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(x) => self.lower_bound(x),
+            Bound::Excluded(x) => self.upper_bound(x),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(x) => self.upper_bound(x),
+            Bound::Excluded(x) => self.lower_bound(x),
+            Bound::Unbounded => self.size(),
+        };
+        (start..end.max(start)).filter_map(|i| self.a.get(i))
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SortedArraySet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.a.iter()).finish()
+    }
+}
+
+impl<T> Default for SortedArraySet<T> {
+    fn default() -> Self {
+        Self {
+            a: ArrayStack::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operation() {
+        // Initialize
+        let mut s = SortedArraySet::with_capacity(0);
+        for x in [5, 1, 9, 3, 7] {
+            assert!(s.add(x));
+            s.check_invariants();
+        }
+        // 重複は追加されない
+        assert!(!s.add(3));
+        s.check_invariants();
+        assert_eq!(s.size(), 5);
+
+        // Find
+        assert_eq!(s.find(&0), Some(&1));
+        assert_eq!(s.find(&3), Some(&3));
+        assert_eq!(s.find(&4), Some(&5));
+        assert_eq!(s.find(&10), None);
+        assert_eq!(s.predecessor(&1), None);
+        assert_eq!(s.predecessor(&4), Some(&3));
+        assert_eq!(s.predecessor(&5), Some(&3));
+        assert_eq!(s.min(), Some(&1));
+        assert_eq!(s.max(), Some(&9));
+
+        // Remove
+        assert_eq!(s.remove(&5), Some(5));
+        s.check_invariants();
+        assert_eq!(s.remove(&5), None);
+        s.check_invariants();
+        assert_eq!(s.find(&4), Some(&7));
+
+        // Check
+        assert_eq!(s.range(..).copied().collect::<Vec<_>>(), vec![1, 3, 7, 9]);
+    }
+
+    #[test]
+    fn range() {
+        let mut s = SortedArraySet::with_capacity(0);
+        for x in (0..20).step_by(2) {
+            s.add(x);
+        }
+
+        let collect = |it: &mut dyn Iterator<Item = &i32>| it.copied().collect::<Vec<_>>();
+        assert_eq!(collect(&mut s.range(3..9)), vec![4, 6, 8]);
+        assert_eq!(collect(&mut s.range(4..=10)), vec![4, 6, 8, 10]);
+        assert_eq!(collect(&mut s.range(..3)), vec![0, 2]);
+        assert_eq!(collect(&mut s.range(15..)), vec![16, 18]);
+        assert_eq!(
+            collect(&mut s.range((Bound::Excluded(4), Bound::Excluded(10)))),
+            vec![6, 8]
+        );
+        assert_eq!(
+            collect(&mut s.range((Bound::Included(9), Bound::Excluded(3)))),
+            Vec::<i32>::new()
+        );
+    }
+}
@@ -0,0 +1,451 @@
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use chapter1::{Capacity, Collection, List, ListError, Stack};
+
+use crate::arraystack::{self, ArrayStack};
+use crate::randomqueue::{RandomSource, random_index};
+use crate::sorted;
+
+/// 重みに比例した確率で要素を取り出すキュー
+///
+/// `tree`は`weights`のFenwick木で、`tree[k]`は`weights`の`k + 1 - lowbit(k + 1)..=k`の和。
+/// 取り出す要素は重みの累積和を木で二分探索して選ぶので、追加も取り出しもO(log n)。
+/// `RandomQueue`と同じく、比較やハッシュは要素と重みの組を昇順に並べた列で行う
+#[derive(Clone)]
+pub struct WeightedRandomQueue<T, R = fastrand::Rng> {
+    items: ArrayStack<T>,
+    weights: ArrayStack<usize>,
+    tree: ArrayStack<usize>,
+    /// 重みの合計
+    total: usize,
+    /// `sample`は`&self`から乱数を引くので`RefCell`に入れておく
+    rng: RefCell<R>,
+}
+
+impl<T> WeightedRandomQueue<T> {
+    /// 乱数の種はスレッドごとの乱数から選ぶ
+    pub fn with_capacity(len: usize) -> Self {
+        let mut q = Self::with_rng(fastrand::Rng::new());
+        q.reserve(len);
+        q
+    }
+
+    /// 乱数の種を指定する。同じ種なら同じ順序で取り出される
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(fastrand::Rng::with_seed(seed))
+    }
+}
+
+impl<T, R> WeightedRandomQueue<T, R> {
+    /// 乱数源を指定する
+    pub fn with_rng(rng: R) -> Self {
+        Self {
+            items: ArrayStack::with_capacity(0),
+            weights: ArrayStack::with_capacity(0),
+            tree: ArrayStack::with_capacity(0),
+            total: 0,
+            rng: RefCell::new(rng),
+        }
+    }
+
+    /// 重みの合計を返す
+    pub fn total_weight(&self) -> usize {
+        self.total
+    }
+
+    /// 要素と重みの組を格納順に返す。`remove`で取り出される順ではない
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&T, usize)> + ExactSizeIterator {
+        self.items.iter().zip(self.weights.iter().copied())
+    }
+
+    /// `x`を重み`weight`で追加する
+    ///
+    /// 重み0の要素は、重みが正の要素がなくなるまで取り出されない。
+    /// 重みの合計が`usize`に収まらない場合は追加せずに`x`を返す
+    pub fn add(&mut self, x: T, weight: usize) -> Result<(), ListError<T>> {
+        let Some(total) = self.total.checked_add(weight) else {
+            return Err(ListError::CapacityExhausted(x));
+        };
+        self.items.push(x)?;
+        self.total = total;
+
+        // 新しい位置kの木の値は、重みにk - lowbit(k)..kの和を足したもの
+        let k = self.weights.size() + 1;
+        let covered = self.prefix_sum(k - 1) - self.prefix_sum(k - lowbit(k));
+        let pushed = self
+            .weights
+            .push(weight)
+            .and_then(|()| self.tree.push(weight + covered));
+        debug_assert!(pushed.is_ok());
+        Ok(())
+    }
+
+    /// 先頭から`k`個の重みの和を返す
+    fn prefix_sum(&self, mut k: usize) -> usize {
+        let mut sum = 0;
+        while k > 0 {
+            sum += self.tree[k - 1];
+            k -= lowbit(k);
+        }
+        sum
+    }
+
+    /// `i`番目の重みを`old`から`new`に変える
+    fn update(&mut self, i: usize, old: usize, new: usize) {
+        let mut k = i + 1;
+        while k <= self.tree.size() {
+            // 木の値は`old`を含んでいるので引いても負にならない
+            self.tree[k - 1] = self.tree[k - 1] - old + new;
+            k += lowbit(k);
+        }
+        self.weights[i] = new;
+    }
+
+    /// 重みの累積和が`r`を超える最初の要素のインデックスを返す
+    fn find(&self, mut r: usize) -> usize {
+        let n = self.tree.size();
+        let mut k = 0;
+        let mut step = if n == 0 { 0 } else { 1 << n.ilog2() };
+        while step > 0 {
+            if k + step <= n && self.tree[k + step - 1] <= r {
+                k += step;
+                r -= self.tree[k - 1];
+            }
+            step /= 2;
+        }
+        k
+    }
+
+    /// 内部の状態が不変条件を満たすかを確かめ、満たさなければpanicする
+    ///
+    /// 要素・重み・木の長さが揃い、`total`が重みの合計で、木の各値が受け持つ区間の重みの和であること
+    #[cfg(any(test, feature = "debug-invariants"))]
+    pub fn check_invariants(&self) {
+        self.items.check_invariants();
+        self.weights.check_invariants();
+        self.tree.check_invariants();
+        let n = self.items.size();
+        assert_eq!(self.weights.size(), n, "number of weights");
+        assert_eq!(self.tree.size(), n, "size of the tree");
+        assert_eq!(
+            self.total,
+            self.weights.iter().sum::<usize>(),
+            "total weight"
+        );
+        for k in 1..=n {
+            let sum = self.weights.as_slice()[k - lowbit(k)..k]
+                .iter()
+                .sum::<usize>();
+            assert_eq!(self.tree[k - 1], sum, "tree[{}]", k - 1);
+        }
+    }
+}
+
+impl<T, R: RandomSource> WeightedRandomQueue<T, R> {
+    /// 重みに比例した確率で選んだ要素のインデックスを返す。要素は1つ以上
+    ///
+    /// 乱数源が範囲外の値を返したら、キューを変更する前にここでpanicする
+    fn choose(&self) -> usize {
+        let mut rng = self.rng.borrow_mut();
+        if self.total == 0 {
+            // 重みが正の要素がなければ一様に選ぶ
+            random_index(&mut *rng, self.items.size())
+        } else {
+            self.find(random_index(&mut *rng, self.total))
+        }
+    }
+
+    /// 重みに比例した確率で選んだ要素を取り除いて返す
+    pub fn remove(&mut self) -> Option<T> {
+        let n = self.items.size();
+        if n == 0 {
+            return None;
+        }
+        let i = self.choose();
+
+        // 末尾を木から取り除いてから、その重みをiへ移す。逆の順序では、
+        // iと末尾の両方を受け持つ木の値が一時的に重みの合計を超えてあふれることがある
+        let last = n - 1;
+        let last_weight = self.weights[last];
+        self.weights.pop();
+        self.tree.pop();
+        let weight = if i != last {
+            let weight = self.weights[i];
+            self.update(i, weight, last_weight);
+            self.items.as_mut_slice().swap(i, last);
+            weight
+        } else {
+            last_weight
+        };
+        self.total -= weight;
+        self.items.pop()
+    }
+
+    /// 重みに比例した確率で選んだ要素を、取り除かずに返す
+    pub fn sample(&self) -> Option<&T> {
+        let n = self.items.size();
+        if n == 0 {
+            return None;
+        }
+        let i = self.choose();
+        self.items.get(i)
+    }
+}
+
+impl<T, R> Collection for WeightedRandomQueue<T, R> {
+    fn size(&self) -> usize {
+        self.items.size()
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        self.weights.clear();
+        self.tree.clear();
+        self.total = 0;
+    }
+}
+
+impl<T, R> Capacity for WeightedRandomQueue<T, R> {
+    fn capacity(&self) -> usize {
+        self.items.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.items.reserve(additional);
+        self.weights.reserve(additional);
+        self.tree.reserve(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.items.shrink_to_fit();
+        self.weights.shrink_to_fit();
+        self.tree.shrink_to_fit();
+    }
+}
+
+/// `(要素, 重み)`の組を追加する
+impl<T, R> Extend<(T, usize)> for WeightedRandomQueue<T, R> {
+    fn extend<I: IntoIterator<Item = (T, usize)>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (x, weight) in iter {
+            let added = self.add(x, weight);
+            assert!(added.is_ok(), "total weight overflow");
+        }
+    }
+}
+
+impl<T, R: Default> FromIterator<(T, usize)> for WeightedRandomQueue<T, R> {
+    fn from_iter<I: IntoIterator<Item = (T, usize)>>(iter: I) -> Self {
+        let mut q = Self::with_rng(R::default());
+        q.extend(iter);
+        q
+    }
+}
+
+/// 要素を格納順に取り出すイテレータ
+impl<T, R> IntoIterator for WeightedRandomQueue<T, R> {
+    type Item = T;
+    type IntoIter = arraystack::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+/// `(要素, 重み)`の組を格納順に表示する
+impl<T: fmt::Debug, R> fmt::Debug for WeightedRandomQueue<T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// 格納順や乱数源の状態によらず、要素と重みの同じ組を同じ数だけ持っていれば等しい
+impl<T: Ord, R> PartialEq for WeightedRandomQueue<T, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && sorted(self.iter()) == sorted(other.iter())
+    }
+}
+
+impl<T: Ord, R> Eq for WeightedRandomQueue<T, R> {}
+
+impl<T: Ord + Hash, R> Hash for WeightedRandomQueue<T, R> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size());
+        for x in sorted(self.iter()) {
+            x.hash(state);
+        }
+    }
+}
+
+/// 要素と重みの組を昇順に並べた列を辞書式に比べる
+impl<T: Ord, R> PartialOrd for WeightedRandomQueue<T, R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord, R> Ord for WeightedRandomQueue<T, R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        sorted(self.iter()).cmp(&sorted(other.iter()))
+    }
+}
+
+impl<T, R: Default> Default for WeightedRandomQueue<T, R> {
+    fn default() -> Self {
+        Self::with_rng(R::default())
+    }
+}
+
+/// 最下位の立っているビット
+fn lowbit(k: usize) -> usize {
+    k & k.wrapping_neg()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operation() {
+        let mut q = WeightedRandomQueue::with_seed(0);
+        assert_eq!(q.remove(), None);
+        q.check_invariants();
+        assert_eq!(q.sample(), None);
+        for x in 0..100 {
+            q.add(x, x % 7).unwrap();
+            q.check_invariants();
+        }
+        assert_eq!(q.total_weight(), (0..100).map(|x| x % 7).sum::<usize>());
+
+        // 重みの合計は取り出した要素の分だけ減り、重み0の要素は最後に取り出される
+        let mut removed = Vec::new();
+        while let Some(x) = q.remove() {
+            q.check_invariants();
+            if x % 7 == 0 {
+                assert_eq!(q.total_weight(), 0);
+            }
+            removed.push(x);
+        }
+        removed.sort();
+        assert!(removed.into_iter().eq(0..100));
+        assert_eq!(q.total_weight(), 0);
+    }
+
+    #[test]
+    fn fenwick() {
+        // 末尾との入れ替えで削除しても、木がすべての累積和を保っている
+        let mut q = WeightedRandomQueue::with_seed(1);
+        q.extend((0..50).map(|x| (x, x + 1)));
+        for _ in 0..20 {
+            q.remove();
+            q.check_invariants();
+            let mut sum = 0;
+            for (k, (_, w)) in q.iter().enumerate() {
+                assert_eq!(q.prefix_sum(k), sum);
+                // 累積和が`sum..sum + w`の乱数ならk番目が選ばれる
+                assert_eq!(q.find(sum), k);
+                assert_eq!(q.find(sum + w - 1), k);
+                sum += w;
+            }
+        }
+    }
+
+    #[test]
+    fn proportional() {
+        // 重み1:2:3:4の要素から1つ取り出すことを繰り返し、カイ二乗検定で確かめる
+        const TRIALS: usize = 100_000;
+        let weights = [1, 2, 3, 4];
+        let mut rng = fastrand::Rng::with_seed(0);
+        let mut counts = [0; 4];
+        let mut sampled = [0; 4];
+        for _ in 0..TRIALS {
+            let mut q = WeightedRandomQueue::with_rng(rng.fork());
+            q.extend(weights.iter().enumerate().map(|(i, &w)| (i, w)));
+            sampled[*q.sample().unwrap()] += 1;
+            counts[q.remove().unwrap()] += 1;
+        }
+
+        let chi2 = |counts: &[usize]| {
+            counts
+                .iter()
+                .zip(weights)
+                .map(|(&c, w)| {
+                    let expected = (TRIALS * w / 10) as f64;
+                    (c as f64 - expected).powi(2) / expected
+                })
+                .sum::<f64>()
+        };
+        // 自由度3のカイ二乗分布の上側0.1%点
+        assert!(chi2(&counts) < 16.27, "{counts:?}");
+        assert!(chi2(&sampled) < 16.27, "{sampled:?}");
+    }
+
+    #[test]
+    fn overflow() {
+        let mut q = WeightedRandomQueue::with_seed(0);
+        q.add('a', usize::MAX).unwrap();
+        assert_eq!(q.add('b', 1), Err(ListError::CapacityExhausted('b')));
+        assert_eq!(q.size(), 1);
+        q.check_invariants();
+        assert_eq!(q.remove(), Some('a'));
+        q.add('b', 1).unwrap();
+        q.check_invariants();
+
+        // 合計がちょうど`usize::MAX`になる重みでも、どちらを取り出してもあふれない
+        for seed in 0..8 {
+            let mut q = WeightedRandomQueue::with_seed(seed);
+            q.add('a', 1 << 62).unwrap();
+            q.add('b', usize::MAX - (1 << 62)).unwrap();
+            let x = q.remove().unwrap();
+            q.check_invariants();
+            let y = q.remove().unwrap();
+            assert_ne!(x, y);
+            assert_eq!(q.total_weight(), 0);
+        }
+    }
+
+    #[test]
+    fn traits() {
+        use std::hash::{BuildHasher, RandomState};
+
+        // 格納順によらず、要素と重みの組が同じなら等しい
+        let mut a = WeightedRandomQueue::with_seed(1);
+        a.extend([('a', 1), ('b', 2), ('a', 1)]);
+        let mut b = WeightedRandomQueue::with_seed(2);
+        b.extend([('b', 2), ('a', 1), ('a', 1)]);
+        assert_eq!(a, b);
+        let s = RandomState::new();
+        assert_eq!(s.hash_one(&a), s.hash_one(&b));
+
+        // 同じ要素でも重みが違えば等しくない
+        let mut c = WeightedRandomQueue::with_seed(1);
+        c.extend([('a', 2), ('b', 1), ('a', 1)]);
+        assert_ne!(a, c);
+        // ('a', 1), ('a', 1), ...と('a', 1), ('a', 2), ...
+        assert!(a < c);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(a.clone(), a);
+    }
+
+    #[test]
+    fn bad_random_source() {
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        // 範囲外の`n`を返す乱数源でも、キューを変更する前にpanicする
+        let mut q = WeightedRandomQueue::with_rng(|n: usize| n);
+        q.extend([('a', 1), ('b', 2), ('c', 0)]);
+        let err = catch_unwind(AssertUnwindSafe(|| q.remove())).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<String>().unwrap(),
+            "RandomSource::index(3) returned 3"
+        );
+        q.check_invariants();
+        assert_eq!(q.size(), 3);
+        assert_eq!(q.total_weight(), 3);
+        assert!(catch_unwind(AssertUnwindSafe(|| q.sample())).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! `Deque`の実装を同じ操作列で動かし、`VecDeque`の結果と比べる。操作のたびに内部の不変条件も確かめる
+//!
+//! `cargo +nightly fuzz run deque`で実行する
+
+#![no_main]
+
+use std::collections::VecDeque;
+
+use arbitrary::Arbitrary;
+use chapter1::{Deque, List};
+use chapter2::arraydeque::ArrayDeque;
+use chapter2::dualarraydeque::DualArrayDeque;
+use chapter2::rootisharraydeque::RootishArrayDeque;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    AddFront(u32),
+    AddBack(u32),
+    RemoveFront,
+    RemoveBack,
+    /// 先頭の要素を書き換える
+    SetFront(u32),
+    /// 末尾の要素を書き換える
+    SetBack(u32),
+    Clear,
+    /// 要素数+1で割った余りの数だけ回転させる
+    RotateLeft(usize),
+    RotateRight(usize),
+    MakeContiguous,
+}
+
+/// 回転と並べ直し。`ArrayDeque`以外は`Deque`の操作で回転させ、並べ直しは何もしない
+trait Rotate {
+    fn rotate_left(&mut self, r: usize);
+    fn rotate_right(&mut self, r: usize);
+    fn make_contiguous(&mut self) {}
+}
+
+impl Rotate for ArrayDeque<u32> {
+    fn rotate_left(&mut self, r: usize) {
+        ArrayDeque::rotate_left(self, r);
+    }
+
+    fn rotate_right(&mut self, r: usize) {
+        ArrayDeque::rotate_right(self, r);
+    }
+
+    fn make_contiguous(&mut self) {
+        ArrayDeque::make_contiguous(self);
+    }
+}
+
+macro_rules! impl_rotate_by_deque {
+    ($($ty:ty),*) => {$(
+        impl Rotate for $ty {
+            fn rotate_left(&mut self, r: usize) {
+                for _ in 0..r {
+                    let x = self.remove_front().unwrap();
+                    self.add_back(x).unwrap();
+                }
+            }
+
+            fn rotate_right(&mut self, r: usize) {
+                for _ in 0..r {
+                    let x = self.remove_back().unwrap();
+                    self.add_front(x).unwrap();
+                }
+            }
+        }
+    )*};
+}
+
+impl_rotate_by_deque!(DualArrayDeque<u32>, RootishArrayDeque<u32>);
+
+/// 操作の結果。取り除いた要素か、書き換えた要素の元の値
+fn apply<D: Deque<u32> + Rotate>(deque: &mut D, op: &Op) -> Option<u32> {
+    match *op {
+        Op::AddFront(x) => deque.add_front(x).err().map(|e| e.into_inner()),
+        Op::AddBack(x) => deque.add_back(x).err().map(|e| e.into_inner()),
+        Op::RemoveFront => deque.remove_front(),
+        Op::RemoveBack => deque.remove_back(),
+        Op::SetFront(x) => deque.front_mut().map(|y| core::mem::replace(y, x)),
+        Op::SetBack(x) => deque.back_mut().map(|y| core::mem::replace(y, x)),
+        Op::Clear => {
+            deque.clear();
+            None
+        }
+        Op::RotateLeft(r) => {
+            deque.rotate_left(r % (deque.size() + 1));
+            None
+        }
+        Op::RotateRight(r) => {
+            deque.rotate_right(r % (deque.size() + 1));
+            None
+        }
+        Op::MakeContiguous => {
+            deque.make_contiguous();
+            None
+        }
+    }
+}
+
+fn apply_vec(v: &mut VecDeque<u32>, op: &Op) -> Option<u32> {
+    match *op {
+        Op::AddFront(x) => {
+            v.push_front(x);
+            None
+        }
+        Op::AddBack(x) => {
+            v.push_back(x);
+            None
+        }
+        Op::RemoveFront => v.pop_front(),
+        Op::RemoveBack => v.pop_back(),
+        Op::SetFront(x) => v.front_mut().map(|y| core::mem::replace(y, x)),
+        Op::SetBack(x) => v.back_mut().map(|y| core::mem::replace(y, x)),
+        Op::Clear => {
+            v.clear();
+            None
+        }
+        Op::RotateLeft(r) => {
+            v.rotate_left(r % (v.len() + 1));
+            None
+        }
+        Op::RotateRight(r) => {
+            v.rotate_right(r % (v.len() + 1));
+            None
+        }
+        Op::MakeContiguous => {
+            v.make_contiguous();
+            None
+        }
+    }
+}
+
+/// 操作の結果と、操作後の要素の列が`VecDeque`と一致することを確かめる
+fn check<D: Deque<u32> + List<u32> + Rotate>(
+    name: &str,
+    deque: &mut D,
+    op: &Op,
+    v: &VecDeque<u32>,
+    expected: Option<u32>,
+) {
+    assert_eq!(apply(deque, op), expected, "{name}: {op:?}");
+    assert_eq!(deque.size(), v.len(), "{name}: size after {op:?}");
+    assert_eq!(deque.front(), v.front(), "{name}: front after {op:?}");
+    assert_eq!(deque.back(), v.back(), "{name}: back after {op:?}");
+    for (i, x) in v.iter().enumerate() {
+        assert_eq!(
+            List::get(deque, i),
+            Some(x),
+            "{name}: get({i}) after {op:?}"
+        );
+    }
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut v = VecDeque::new();
+    let mut deque = ArrayDeque::with_capacity(0);
+    let mut dual = DualArrayDeque::with_capacity(0);
+    let mut rootish = RootishArrayDeque::with_capacity();
+    for op in &ops {
+        let expected = apply_vec(&mut v, op);
+        check("ArrayDeque", &mut deque, op, &v, expected);
+        deque.check_invariants();
+        check("DualArrayDeque", &mut dual, op, &v, expected);
+        dual.check_invariants();
+        check("RootishArrayDeque", &mut rootish, op, &v, expected);
+        rootish.check_invariants();
+    }
+});
@@ -0,0 +1,129 @@
+//! `List`の実装を同じ操作列で動かし、`Vec`の結果と比べる。操作のたびに内部の不変条件も確かめる
+//!
+//! `cargo +nightly fuzz run list`で実行する
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use chapter1::{Capacity, List, ListError};
+use chapter2::arraydeque::ArrayDeque;
+use chapter2::arraystack::ArrayStack;
+use chapter2::dualarraydeque::DualArrayDeque;
+use chapter2::rootisharraystack::RootishArrayStack;
+use libfuzzer_sys::fuzz_target;
+
+/// インデックスは要素数`n`に対して`0..n + 2`に丸めるので、範囲外の`n + 1`も含む
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Get(u16),
+    Set(u16, u32),
+    Add(u16, u32),
+    Remove(u16),
+    Clear,
+    Reserve(u8),
+    ShrinkToFit,
+}
+
+/// 操作の結果。実装ごとの結果を`Vec`の結果と比べる
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Get(Option<u32>),
+    Set(Result<u32, ListError<u32>>),
+    Add(Result<(), ListError<u32>>),
+    Remove(Option<u32>),
+    None,
+}
+
+fn apply<L: List<u32> + Capacity>(list: &mut L, op: &Op, n: usize) -> Outcome {
+    let index = |i: &u16| usize::from(*i) % (n + 2);
+    match op {
+        Op::Get(i) => Outcome::Get(list.get(index(i)).copied()),
+        Op::Set(i, x) => Outcome::Set(list.set(index(i), *x)),
+        Op::Add(i, x) => Outcome::Add(list.add(index(i), *x)),
+        Op::Remove(i) => Outcome::Remove(list.remove(index(i))),
+        Op::Clear => {
+            list.clear();
+            Outcome::None
+        }
+        Op::Reserve(k) => {
+            list.reserve(usize::from(*k));
+            Outcome::None
+        }
+        Op::ShrinkToFit => {
+            list.shrink_to_fit();
+            Outcome::None
+        }
+    }
+}
+
+fn apply_vec(v: &mut Vec<u32>, op: &Op) -> Outcome {
+    let n = v.len();
+    let index = |i: &u16| usize::from(*i) % (n + 2);
+    let out_of_bounds = |index, value| ListError::OutOfBounds {
+        index,
+        size: n,
+        value,
+    };
+    match op {
+        Op::Get(i) => Outcome::Get(v.get(index(i)).copied()),
+        Op::Set(i, x) => Outcome::Set(match v.get_mut(index(i)) {
+            Some(y) => Ok(core::mem::replace(y, *x)),
+            None => Err(out_of_bounds(index(i), *x)),
+        }),
+        Op::Add(i, x) if index(i) <= n => {
+            v.insert(index(i), *x);
+            Outcome::Add(Ok(()))
+        }
+        Op::Add(i, x) => Outcome::Add(Err(out_of_bounds(index(i), *x))),
+        Op::Remove(i) => Outcome::Remove((index(i) < n).then(|| v.remove(index(i)))),
+        Op::Clear => {
+            v.clear();
+            Outcome::None
+        }
+        Op::Reserve(_) | Op::ShrinkToFit => Outcome::None,
+    }
+}
+
+/// 操作の結果と、操作後の要素の列が`Vec`と一致することを確かめる
+fn check<L: List<u32> + Capacity>(
+    name: &str,
+    list: &mut L,
+    op: &Op,
+    v: &[u32],
+    expected: &Outcome,
+) {
+    let n = list.size();
+    assert_eq!(&apply(list, op, n), expected, "{name}: {op:?}");
+    assert_eq!(list.size(), v.len(), "{name}: size after {op:?}");
+    assert!(
+        list.capacity() >= list.size(),
+        "{name}: capacity after {op:?}"
+    );
+    for (i, x) in v.iter().enumerate() {
+        assert_eq!(list.get(i), Some(x), "{name}: get({i}) after {op:?}");
+    }
+    assert_eq!(
+        list.get(v.len()),
+        None,
+        "{name}: get past the end after {op:?}"
+    );
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut v = Vec::new();
+    let mut stack = ArrayStack::with_capacity(0);
+    let mut deque = ArrayDeque::with_capacity(0);
+    let mut dual = DualArrayDeque::with_capacity(0);
+    let mut rootish = RootishArrayStack::with_capacity();
+    for op in &ops {
+        let expected = apply_vec(&mut v, op);
+        check("ArrayStack", &mut stack, op, &v, &expected);
+        stack.check_invariants();
+        check("ArrayDeque", &mut deque, op, &v, &expected);
+        deque.check_invariants();
+        check("DualArrayDeque", &mut dual, op, &v, &expected);
+        dual.check_invariants();
+        check("RootishArrayStack", &mut rootish, op, &v, &expected);
+        rootish.check_invariants();
+    }
+});